@@ -0,0 +1,102 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use md5::{Digest, Md5};
+
+use crate::makefile::TargetData;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredAsset {
+    pub asset_id: String,
+    /// lowercase file extension
+    pub data_format: String,
+    pub md5ext: String,
+    pub content: Vec<u8>,
+    /// the first project.toml entry using the file, for errors
+    pub entry: String,
+    pub path: PathBuf,
+}
+
+/// every costume file in a project, hashed once and deduplicated by hash,
+/// the compiler names costumes and the packager writes files from the same store,
+/// sounds aren't stored because they aren't compiled or packaged yet
+#[derive(Debug, Clone, Default)]
+pub struct AssetStore {
+    assets: Vec<StoredAsset>,
+    /// HashMap<Path, Index>
+    by_path: HashMap<PathBuf, usize>,
+    /// HashMap<Md5ext, Index>
+    by_md5ext: HashMap<String, usize>,
+}
+
+impl AssetStore {
+    pub fn new(targets: &[TargetData]) -> AssetStore {
+        let mut store = AssetStore::default();
+
+        for target in targets {
+            let (table, key) = if target.is_stage {
+                ("[[stage]]", "backdrops")
+            } else {
+                ("[[sprite]]", "costumes")
+            };
+
+            for costume in &target.costumes {
+                if store.by_path.contains_key(&costume.path) {
+                    continue;
+                }
+
+                let mut hasher = Md5::new();
+                hasher.update(&costume.content);
+                let asset_id = format!("{:x}", hasher.finalize());
+
+                let data_format = costume
+                    .path
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .unwrap_or_default()
+                    .to_lowercase();
+                let md5ext = format!("{}.{}", asset_id, data_format);
+
+                // identical files at different paths are only stored once
+                let index = match store.by_md5ext.get(&md5ext) {
+                    Some(index) => *index,
+                    None => {
+                        store.assets.push(StoredAsset {
+                            asset_id,
+                            data_format,
+                            md5ext: md5ext.clone(),
+                            content: costume.content.clone(),
+                            entry: format!(
+                                "{} {:?} {} {:?}",
+                                table, target.name, key, costume.name
+                            ),
+                            path: costume.path.clone(),
+                        });
+                        store.by_md5ext.insert(md5ext, store.assets.len() - 1);
+                        store.assets.len() - 1
+                    }
+                };
+
+                store.by_path.insert(costume.path.clone(), index);
+            }
+        }
+
+        store
+    }
+
+    pub fn get(&self, path: &Path) -> &StoredAsset {
+        let index = self
+            .by_path
+            .get(path)
+            .unwrap_or_else(|| panic!("asset {} is not in the asset store", path.display()));
+
+        &self.assets[*index]
+    }
+
+    /// every unique asset, in the order they first appear in project.toml
+    pub fn iter(&self) -> impl Iterator<Item = &StoredAsset> {
+        self.assets.iter()
+    }
+}
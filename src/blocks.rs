@@ -0,0 +1,1361 @@
+// declarative definitions for every built-in block, the compiler,
+// the type checker and the docs generator all read from this table
+
+use crate::makefile::Extension;
+use crate::token::Type;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Stack,
+    /// a stack block nothing can be attached under, e.g. "delete this clone"
+    Cap,
+    Hat,
+    Reporter(Type),
+}
+
+#[derive(Debug)]
+pub struct Menu {
+    pub opcode: &'static str,
+    pub field: &'static str,
+    /// (name, value), where name is what's written in scuff source
+    pub options: &'static [(&'static str, &'static str)],
+    /// whether reporters can be dropped over the menu
+    pub reporters: bool,
+    /// whether unlisted string literals are accepted, e.g. sprite names
+    pub open: bool,
+}
+
+#[derive(Debug)]
+pub enum Input {
+    Number,
+    Text,
+    Bool,
+    /// `"#rrggbb"`
+    Color,
+    /// midi note, shown as a piano
+    Note,
+    Menu(&'static Menu),
+    /// a dropdown that lives in the block's fields rather than its inputs,
+    /// (name, value) like `Menu::options`
+    Field(&'static [(&'static str, &'static str)]),
+}
+
+#[derive(Debug)]
+pub struct BlockDef {
+    /// name of the built-in function in scuff source
+    pub name: &'static str,
+    pub opcode: &'static str,
+    /// (input or field name, kind), in argument order
+    pub inputs: &'static [(&'static str, Input)],
+    /// fields that are always set to the same value
+    pub fields: &'static [(&'static str, &'static str)],
+    pub shape: Shape,
+    pub extension: Option<Extension>,
+}
+
+impl BlockDef {
+    const fn new(
+        name: &'static str,
+        opcode: &'static str,
+        inputs: &'static [(&'static str, Input)],
+        shape: Shape,
+    ) -> BlockDef {
+        BlockDef {
+            name,
+            opcode,
+            inputs,
+            fields: &[],
+            shape,
+            extension: None,
+        }
+    }
+
+    const fn with_fields(self, fields: &'static [(&'static str, &'static str)]) -> BlockDef {
+        BlockDef { fields, ..self }
+    }
+
+    const fn requires(self, extension: Extension) -> BlockDef {
+        BlockDef {
+            extension: Some(extension),
+            ..self
+        }
+    }
+
+    pub fn return_type(&self) -> Option<Type> {
+        match &self.shape {
+            Shape::Reporter(return_type) => Some(return_type.clone()),
+            _ => None,
+        }
+    }
+}
+
+pub fn lookup(name: &str) -> Option<&'static BlockDef> {
+    BLOCKS.iter().find(|block| block.name == name)
+}
+
+impl Menu {
+    /// resolves an option name (or value) written in source to its value
+    pub fn resolve(&self, option: &str) -> Option<String> {
+        resolve_option(self.options, option)
+    }
+}
+
+pub fn resolve_option(options: &[(&str, &str)], option: &str) -> Option<String> {
+    options
+        .iter()
+        .find(|(name, value)| *name == option || *value == option)
+        .map(|(_, value)| value.to_string())
+}
+
+const fn stack(
+    name: &'static str,
+    opcode: &'static str,
+    inputs: &'static [(&'static str, Input)],
+) -> BlockDef {
+    BlockDef::new(name, opcode, inputs, Shape::Stack)
+}
+
+const fn cap(
+    name: &'static str,
+    opcode: &'static str,
+    inputs: &'static [(&'static str, Input)],
+) -> BlockDef {
+    BlockDef::new(name, opcode, inputs, Shape::Cap)
+}
+
+const fn hat(
+    name: &'static str,
+    opcode: &'static str,
+    inputs: &'static [(&'static str, Input)],
+) -> BlockDef {
+    BlockDef::new(name, opcode, inputs, Shape::Hat)
+}
+
+const fn reporter(
+    name: &'static str,
+    opcode: &'static str,
+    inputs: &'static [(&'static str, Input)],
+    return_type: Type,
+) -> BlockDef {
+    BlockDef::new(name, opcode, inputs, Shape::Reporter(return_type))
+}
+
+const fn menu(
+    opcode: &'static str,
+    field: &'static str,
+    options: &'static [(&'static str, &'static str)],
+) -> Menu {
+    Menu {
+        opcode,
+        field,
+        options,
+        reporters: true,
+        open: false,
+    }
+}
+
+// core menus
+
+const GOTO_MENU: Menu = Menu {
+    open: true,
+    ..menu(
+        "motion_goto_menu",
+        "TO",
+        &[
+            ("random_position", "_random_"),
+            ("mouse_pointer", "_mouse_"),
+        ],
+    )
+};
+
+const POINT_TOWARDS_MENU: Menu = Menu {
+    open: true,
+    ..menu(
+        "motion_pointtowards_menu",
+        "TOWARDS",
+        &[("mouse_pointer", "_mouse_")],
+    )
+};
+
+const ROTATION_STYLES: &[(&str, &str)] = &[
+    ("left_right", "left-right"),
+    ("dont_rotate", "don't rotate"),
+    ("all_around", "all around"),
+];
+
+const COSTUME_MENU: Menu = Menu {
+    open: true,
+    ..menu("looks_costume", "COSTUME", &[])
+};
+
+const BACKDROP_MENU: Menu = Menu {
+    open: true,
+    ..menu("looks_backdrops", "BACKDROP", &[])
+};
+
+const EFFECTS: &[(&str, &str)] = &[
+    ("color", "COLOR"),
+    ("fisheye", "FISHEYE"),
+    ("whirl", "WHIRL"),
+    ("pixelate", "PIXELATE"),
+    ("mosaic", "MOSAIC"),
+    ("brightness", "BRIGHTNESS"),
+    ("ghost", "GHOST"),
+];
+
+const FRONT_BACK: &[(&str, &str)] = &[("front", "front"), ("back", "back")];
+
+const FORWARD_BACKWARD: &[(&str, &str)] = &[("forward", "forward"), ("backward", "backward")];
+
+const SOUND_MENU: Menu = Menu {
+    open: true,
+    ..menu("sound_sounds_menu", "SOUND_MENU", &[])
+};
+
+const CLONE_MENU: Menu = Menu {
+    open: true,
+    ..menu(
+        "control_create_clone_of_menu",
+        "CLONE_OPTION",
+        &[("myself", "_myself_")],
+    )
+};
+
+const TOUCHING_MENU: Menu = Menu {
+    open: true,
+    ..menu(
+        "sensing_touchingobjectmenu",
+        "TOUCHINGOBJECTMENU",
+        &[("mouse_pointer", "_mouse_"), ("edge", "_edge_")],
+    )
+};
+
+const DISTANCE_MENU: Menu = Menu {
+    open: true,
+    ..menu(
+        "sensing_distancetomenu",
+        "DISTANCETOMENU",
+        &[("mouse_pointer", "_mouse_")],
+    )
+};
+
+const KEY_MENU: Menu = Menu {
+    open: true,
+    ..menu(
+        "sensing_keyoptions",
+        "KEY_OPTION",
+        &[
+            ("any", "any"),
+            ("space", "space"),
+            ("up_arrow", "up arrow"),
+            ("down_arrow", "down arrow"),
+            ("left_arrow", "left arrow"),
+            ("right_arrow", "right arrow"),
+        ],
+    )
+};
+
+// extension menus
+
+const PEN_COLOR_PARAM_MENU: Menu = Menu {
+    reporters: false,
+    ..menu(
+        "pen_menu_colorParam",
+        "colorParam",
+        &[
+            ("color", "color"),
+            ("saturation", "saturation"),
+            ("brightness", "brightness"),
+            ("transparency", "transparency"),
+        ],
+    )
+};
+
+const MUSIC_DRUM_MENU: Menu = menu(
+    "music_menu_DRUM",
+    "DRUM",
+    &[
+        ("snare_drum", "1"),
+        ("bass_drum", "2"),
+        ("side_stick", "3"),
+        ("crash_cymbal", "4"),
+        ("open_hi_hat", "5"),
+        ("closed_hi_hat", "6"),
+        ("tambourine", "7"),
+        ("hand_clap", "8"),
+        ("claves", "9"),
+        ("wood_block", "10"),
+        ("cowbell", "11"),
+        ("triangle", "12"),
+        ("bongo", "13"),
+        ("conga", "14"),
+        ("cabasa", "15"),
+        ("guiro", "16"),
+        ("vibraslap", "17"),
+        ("cuica", "18"),
+    ],
+);
+
+const MUSIC_INSTRUMENT_MENU: Menu = menu(
+    "music_menu_INSTRUMENT",
+    "INSTRUMENT",
+    &[
+        ("piano", "1"),
+        ("electric_piano", "2"),
+        ("organ", "3"),
+        ("guitar", "4"),
+        ("electric_guitar", "5"),
+        ("bass", "6"),
+        ("pizzicato", "7"),
+        ("cello", "8"),
+        ("trombone", "9"),
+        ("clarinet", "10"),
+        ("saxophone", "11"),
+        ("flute", "12"),
+        ("wooden_flute", "13"),
+        ("bassoon", "14"),
+        ("choir", "15"),
+        ("vibraphone", "16"),
+        ("music_box", "17"),
+        ("steel_drum", "18"),
+        ("marimba", "19"),
+        ("synth_lead", "20"),
+        ("synth_pad", "21"),
+    ],
+);
+
+const LANGUAGES: &[(&str, &str)] = &[
+    ("arabic", "ar"),
+    ("chinese", "zh-cn"),
+    ("danish", "da"),
+    ("dutch", "nl"),
+    ("english", "en"),
+    ("french", "fr"),
+    ("german", "de"),
+    ("hindi", "hi"),
+    ("icelandic", "is"),
+    ("italian", "it"),
+    ("japanese", "ja"),
+    ("korean", "ko"),
+    ("norwegian", "nb"),
+    ("polish", "pl"),
+    ("brazilian_portuguese", "pt-br"),
+    ("portuguese", "pt"),
+    ("romanian", "ro"),
+    ("russian", "ru"),
+    ("spanish", "es"),
+    ("latin_american_spanish", "es-419"),
+    ("swedish", "sv"),
+    ("turkish", "tr"),
+    ("welsh", "cy"),
+];
+
+const TEXT2SPEECH_VOICE_MENU: Menu = menu(
+    "text2speech_menu_voices",
+    "voices",
+    &[
+        ("alto", "ALTO"),
+        ("tenor", "TENOR"),
+        ("squeak", "SQUEAK"),
+        ("giant", "GIANT"),
+        ("kitten", "KITTEN"),
+    ],
+);
+
+const TEXT2SPEECH_LANGUAGE_MENU: Menu = menu("text2speech_menu_languages", "languages", LANGUAGES);
+
+const TRANSLATE_LANGUAGE_MENU: Menu = menu("translate_menu_languages", "languages", LANGUAGES);
+
+const VIDEO_ATTRIBUTE_MENU: Menu = menu(
+    "videoSensing_menu_ATTRIBUTE",
+    "ATTRIBUTE",
+    &[("motion", "motion"), ("direction", "direction")],
+);
+
+const VIDEO_SUBJECT_MENU: Menu = menu(
+    "videoSensing_menu_SUBJECT",
+    "SUBJECT",
+    &[("sprite", "this sprite"), ("stage", "Stage")],
+);
+
+const VIDEO_STATE_MENU: Menu = menu(
+    "videoSensing_menu_VIDEO_STATE",
+    "VIDEO_STATE",
+    &[("off", "off"), ("on", "on"), ("on_flipped", "on-flipped")],
+);
+
+const MAKEYMAKEY_KEY_MENU: Menu = menu(
+    "makeymakey_menu_KEY",
+    "KEY",
+    &[
+        ("space", "SPACE"),
+        ("left", "LEFT"),
+        ("right", "RIGHT"),
+        ("up", "UP"),
+        ("down", "DOWN"),
+        ("w", "w"),
+        ("a", "a"),
+        ("s", "s"),
+        ("d", "d"),
+        ("f", "f"),
+        ("g", "g"),
+    ],
+);
+
+const MAKEYMAKEY_SEQUENCE_MENU: Menu = menu(
+    "makeymakey_menu_SEQUENCE",
+    "SEQUENCE",
+    &[
+        ("left_up_right", "LEFT UP RIGHT"),
+        ("right_up_left", "RIGHT UP LEFT"),
+        ("left_right", "LEFT RIGHT"),
+        ("right_left", "RIGHT LEFT"),
+        ("up_down", "UP DOWN"),
+        ("down_up", "DOWN UP"),
+        ("up_right_down_left", "UP RIGHT DOWN LEFT"),
+        ("up_left_down_right", "UP LEFT DOWN RIGHT"),
+        (
+            "up_up_down_down_left_right_left_right",
+            "UP UP DOWN DOWN LEFT RIGHT LEFT RIGHT",
+        ),
+    ],
+);
+
+const MICROBIT_BUTTON_MENU: Menu = menu(
+    "microbit_menu_buttons",
+    "buttons",
+    &[("a", "A"), ("b", "B"), ("any", "any")],
+);
+
+const MICROBIT_GESTURE_MENU: Menu = menu(
+    "microbit_menu_gestures",
+    "gestures",
+    &[
+        ("moved", "moved"),
+        ("shaken", "shaken"),
+        ("jumped", "jumped"),
+    ],
+);
+
+const MICROBIT_TILT_ANY_MENU: Menu = menu(
+    "microbit_menu_tiltDirectionAny",
+    "tiltDirectionAny",
+    &[
+        ("front", "front"),
+        ("back", "back"),
+        ("left", "left"),
+        ("right", "right"),
+        ("any", "any"),
+    ],
+);
+
+const MICROBIT_TILT_MENU: Menu = menu(
+    "microbit_menu_tiltDirection",
+    "tiltDirection",
+    &[
+        ("front", "front"),
+        ("back", "back"),
+        ("left", "left"),
+        ("right", "right"),
+    ],
+);
+
+const MICROBIT_PIN_MENU: Menu = menu(
+    "microbit_menu_touchPins",
+    "touchPins",
+    &[("pin_0", "0"), ("pin_1", "1"), ("pin_2", "2")],
+);
+
+const EV3_MOTOR_PORT_MENU: Menu = menu(
+    "ev3_menu_motorPorts",
+    "motorPorts",
+    &[("a", "0"), ("b", "1"), ("c", "2"), ("d", "3")],
+);
+
+const EV3_SENSOR_PORT_MENU: Menu = menu(
+    "ev3_menu_sensorPorts",
+    "sensorPorts",
+    &[
+        ("port_1", "0"),
+        ("port_2", "1"),
+        ("port_3", "2"),
+        ("port_4", "3"),
+    ],
+);
+
+const BOOST_MOTOR_MENU: Menu = menu(
+    "boost_menu_MOTOR_ID",
+    "MOTOR_ID",
+    &[
+        ("a", "A"),
+        ("b", "B"),
+        ("c", "C"),
+        ("d", "D"),
+        ("ab", "AB"),
+        ("all", "ABCD"),
+    ],
+);
+
+const BOOST_MOTOR_REPORTER_MENU: Menu = menu(
+    "boost_menu_MOTOR_REPORTER_ID",
+    "MOTOR_REPORTER_ID",
+    &[("a", "A"), ("b", "B"), ("c", "C"), ("d", "D")],
+);
+
+const BOOST_MOTOR_DIRECTION_MENU: Menu = menu(
+    "boost_menu_MOTOR_DIRECTION",
+    "MOTOR_DIRECTION",
+    &[
+        ("this_way", "this way"),
+        ("that_way", "that way"),
+        ("reverse", "reverse"),
+    ],
+);
+
+const BOOST_COLOR_MENU: Menu = menu(
+    "boost_menu_COLOR",
+    "COLOR",
+    &[
+        ("red", "red"),
+        ("blue", "blue"),
+        ("green", "green"),
+        ("yellow", "yellow"),
+        ("white", "white"),
+        ("black", "black"),
+        ("any", "any"),
+    ],
+);
+
+const BOOST_TILT_ANY_MENU: Menu = menu(
+    "boost_menu_TILT_DIRECTION_ANY",
+    "TILT_DIRECTION_ANY",
+    &[
+        ("up", "up"),
+        ("down", "down"),
+        ("left", "left"),
+        ("right", "right"),
+        ("any", "any"),
+    ],
+);
+
+const BOOST_TILT_MENU: Menu = menu(
+    "boost_menu_TILT_DIRECTION",
+    "TILT_DIRECTION",
+    &[
+        ("up", "up"),
+        ("down", "down"),
+        ("left", "left"),
+        ("right", "right"),
+    ],
+);
+
+const WEDO2_MOTOR_MENU: Menu = menu(
+    "wedo2_menu_MOTOR_ID",
+    "MOTOR_ID",
+    &[
+        ("default", "motor"),
+        ("a", "motor A"),
+        ("b", "motor B"),
+        ("all", "all motors"),
+    ],
+);
+
+const WEDO2_MOTOR_DIRECTION_MENU: Menu = menu(
+    "wedo2_menu_MOTOR_DIRECTION",
+    "MOTOR_DIRECTION",
+    &[
+        ("this_way", "this way"),
+        ("that_way", "that way"),
+        ("reverse", "reverse"),
+    ],
+);
+
+const WEDO2_OP_MENU: Menu = menu("wedo2_menu_OP", "OP", &[("less", "<"), ("greater", ">")]);
+
+const WEDO2_TILT_ANY_MENU: Menu = menu(
+    "wedo2_menu_TILT_DIRECTION_ANY",
+    "TILT_DIRECTION_ANY",
+    &[
+        ("up", "up"),
+        ("down", "down"),
+        ("left", "left"),
+        ("right", "right"),
+        ("any", "any"),
+    ],
+);
+
+const WEDO2_TILT_MENU: Menu = menu(
+    "wedo2_menu_TILT_DIRECTION",
+    "TILT_DIRECTION",
+    &[
+        ("up", "up"),
+        ("down", "down"),
+        ("left", "left"),
+        ("right", "right"),
+    ],
+);
+
+const GDXFOR_GESTURE_MENU: Menu = menu(
+    "gdxfor_menu_gestureOptions",
+    "gestureOptions",
+    &[
+        ("shaken", "shaken"),
+        ("started_falling", "startedFalling"),
+        ("turned_face_up", "turnedFaceUp"),
+        ("turned_face_down", "turnedFaceDown"),
+    ],
+);
+
+const GDXFOR_PUSH_PULL_MENU: Menu = menu(
+    "gdxfor_menu_pushPullOptions",
+    "pushPullOptions",
+    &[("pushed", "pushed"), ("pulled", "pulled")],
+);
+
+const GDXFOR_TILT_ANY_MENU: Menu = menu(
+    "gdxfor_menu_tiltAnyOptions",
+    "tiltAnyOptions",
+    &[
+        ("front", "front"),
+        ("back", "back"),
+        ("left", "left"),
+        ("right", "right"),
+        ("any", "any"),
+    ],
+);
+
+const GDXFOR_TILT_MENU: Menu = menu(
+    "gdxfor_menu_tiltOptions",
+    "tiltOptions",
+    &[
+        ("front", "front"),
+        ("back", "back"),
+        ("left", "left"),
+        ("right", "right"),
+    ],
+);
+
+const GDXFOR_AXIS_MENU: Menu = menu(
+    "gdxfor_menu_axisOptions",
+    "axisOptions",
+    &[("x", "x"), ("y", "y"), ("z", "z")],
+);
+
+use Input::*;
+use Type::{Bool as Boolean, Number as Num, String as Str};
+
+pub const BLOCKS: &[BlockDef] = &[
+    // motion
+    stack("move", "motion_movesteps", &[("STEPS", Number)]),
+    stack("turn_right", "motion_turnright", &[("DEGREES", Number)]),
+    stack("turn_left", "motion_turnleft", &[("DEGREES", Number)]),
+    stack("go_to", "motion_goto", &[("TO", Menu(&GOTO_MENU))]),
+    stack("go_to_xy", "motion_gotoxy", &[("X", Number), ("Y", Number)]),
+    stack(
+        "glide_to_xy",
+        "motion_glidesecstoxy",
+        &[("SECS", Number), ("X", Number), ("Y", Number)],
+    ),
+    stack(
+        "point_in_direction",
+        "motion_pointindirection",
+        &[("DIRECTION", Number)],
+    ),
+    stack(
+        "point_towards",
+        "motion_pointtowards",
+        &[("TOWARDS", Menu(&POINT_TOWARDS_MENU))],
+    ),
+    stack("change_x_by", "motion_changexby", &[("DX", Number)]),
+    stack("set_x", "motion_setx", &[("X", Number)]),
+    stack("change_y_by", "motion_changeyby", &[("DY", Number)]),
+    stack("set_y", "motion_sety", &[("Y", Number)]),
+    stack("bounce_on_edge", "motion_ifonedgebounce", &[]),
+    stack(
+        "set_rotation_style",
+        "motion_setrotationstyle",
+        &[("STYLE", Field(ROTATION_STYLES))],
+    ),
+    reporter("x_position", "motion_xposition", &[], Num),
+    reporter("y_position", "motion_yposition", &[], Num),
+    reporter("direction", "motion_direction", &[], Num),
+    // looks
+    stack("say", "looks_say", &[("MESSAGE", Text)]),
+    stack(
+        "say_for_secs",
+        "looks_sayforsecs",
+        &[("MESSAGE", Text), ("SECS", Number)],
+    ),
+    stack("think", "looks_think", &[("MESSAGE", Text)]),
+    stack(
+        "think_for_secs",
+        "looks_thinkforsecs",
+        &[("MESSAGE", Text), ("SECS", Number)],
+    ),
+    stack(
+        "switch_costume",
+        "looks_switchcostumeto",
+        &[("COSTUME", Menu(&COSTUME_MENU))],
+    ),
+    stack("next_costume", "looks_nextcostume", &[]),
+    stack(
+        "switch_backdrop",
+        "looks_switchbackdropto",
+        &[("BACKDROP", Menu(&BACKDROP_MENU))],
+    ),
+    stack("next_backdrop", "looks_nextbackdrop", &[]),
+    stack(
+        "change_size_by",
+        "looks_changesizeby",
+        &[("CHANGE", Number)],
+    ),
+    stack("set_size", "looks_setsizeto", &[("SIZE", Number)]),
+    stack(
+        "change_effect_by",
+        "looks_changeeffectby",
+        &[("EFFECT", Field(EFFECTS)), ("CHANGE", Number)],
+    ),
+    stack(
+        "set_effect",
+        "looks_seteffectto",
+        &[("EFFECT", Field(EFFECTS)), ("VALUE", Number)],
+    ),
+    stack("clear_graphic_effects", "looks_cleargraphiceffects", &[]),
+    stack("show", "looks_show", &[]),
+    stack("hide", "looks_hide", &[]),
+    stack(
+        "go_to_layer",
+        "looks_gotofrontback",
+        &[("FRONT_BACK", Field(FRONT_BACK))],
+    ),
+    stack(
+        "go_layers",
+        "looks_goforwardbackward",
+        &[
+            ("FORWARD_BACKWARD", Field(FORWARD_BACKWARD)),
+            ("NUM", Number),
+        ],
+    ),
+    reporter("costume_number", "looks_costumenumbername", &[], Num)
+        .with_fields(&[("NUMBER_NAME", "number")]),
+    reporter("costume_name", "looks_costumenumbername", &[], Str)
+        .with_fields(&[("NUMBER_NAME", "name")]),
+    reporter("backdrop_number", "looks_backdropnumbername", &[], Num)
+        .with_fields(&[("NUMBER_NAME", "number")]),
+    reporter("backdrop_name", "looks_backdropnumbername", &[], Str)
+        .with_fields(&[("NUMBER_NAME", "name")]),
+    reporter("size", "looks_size", &[], Num),
+    // sound
+    stack(
+        "play_sound_until_done",
+        "sound_playuntildone",
+        &[("SOUND_MENU", Menu(&SOUND_MENU))],
+    ),
+    stack(
+        "start_sound",
+        "sound_play",
+        &[("SOUND_MENU", Menu(&SOUND_MENU))],
+    ),
+    stack("stop_all_sounds", "sound_stopallsounds", &[]),
+    stack(
+        "change_volume_by",
+        "sound_changevolumeby",
+        &[("VOLUME", Number)],
+    ),
+    stack("set_volume", "sound_setvolumeto", &[("VOLUME", Number)]),
+    reporter("volume", "sound_volume", &[], Num),
+    // control
+    stack("wait", "control_wait", &[("DURATION", Number)]),
+    stack("wait_until", "control_wait_until", &[("CONDITION", Bool)]),
+    stack(
+        "create_clone",
+        "control_create_clone_of",
+        &[("CLONE_OPTION", Menu(&CLONE_MENU))],
+    ),
+    cap("delete_this_clone", "control_delete_this_clone", &[]),
+    hat("start_as_clone", "control_start_as_clone", &[]),
+    // sensing
+    reporter(
+        "touching",
+        "sensing_touchingobject",
+        &[("TOUCHINGOBJECTMENU", Menu(&TOUCHING_MENU))],
+        Boolean,
+    ),
+    reporter(
+        "touching_color",
+        "sensing_touchingcolor",
+        &[("COLOR", Color)],
+        Boolean,
+    ),
+    reporter(
+        "distance_to",
+        "sensing_distanceto",
+        &[("DISTANCETOMENU", Menu(&DISTANCE_MENU))],
+        Num,
+    ),
+    stack("ask", "sensing_askandwait", &[("QUESTION", Text)]),
+    reporter("answer", "sensing_answer", &[], Str),
+    reporter(
+        "key_pressed",
+        "sensing_keypressed",
+        &[("KEY_OPTION", Menu(&KEY_MENU))],
+        Boolean,
+    ),
+    reporter("mouse_down", "sensing_mousedown", &[], Boolean),
+    reporter("mouse_x", "sensing_mousex", &[], Num),
+    reporter("mouse_y", "sensing_mousey", &[], Num),
+    reporter("loudness", "sensing_loudness", &[], Num),
+    reporter("timer", "sensing_timer", &[], Num),
+    stack("reset_timer", "sensing_resettimer", &[]),
+    reporter("days_since_2000", "sensing_dayssince2000", &[], Num),
+    reporter("username", "sensing_username", &[], Str),
+    // operators
+    reporter(
+        "random",
+        "operator_random",
+        &[("FROM", Number), ("TO", Number)],
+        Num,
+    ),
+    reporter("round", "operator_round", &[("NUM", Number)], Num),
+    reporter(
+        "mod",
+        "operator_mod",
+        &[("NUM1", Number), ("NUM2", Number)],
+        Num,
+    ),
+    reporter("length", "operator_length", &[("STRING", Text)], Num),
+    reporter(
+        "letter_of",
+        "operator_letter_of",
+        &[("LETTER", Number), ("STRING", Text)],
+        Str,
+    ),
+    reporter(
+        "contains",
+        "operator_contains",
+        &[("STRING1", Text), ("STRING2", Text)],
+        Boolean,
+    ),
+    reporter("abs", "operator_mathop", &[("NUM", Number)], Num).with_fields(&[("OPERATOR", "abs")]),
+    reporter("floor", "operator_mathop", &[("NUM", Number)], Num)
+        .with_fields(&[("OPERATOR", "floor")]),
+    reporter("ceiling", "operator_mathop", &[("NUM", Number)], Num)
+        .with_fields(&[("OPERATOR", "ceiling")]),
+    reporter("sqrt", "operator_mathop", &[("NUM", Number)], Num)
+        .with_fields(&[("OPERATOR", "sqrt")]),
+    reporter("sin", "operator_mathop", &[("NUM", Number)], Num).with_fields(&[("OPERATOR", "sin")]),
+    reporter("cos", "operator_mathop", &[("NUM", Number)], Num).with_fields(&[("OPERATOR", "cos")]),
+    reporter("tan", "operator_mathop", &[("NUM", Number)], Num).with_fields(&[("OPERATOR", "tan")]),
+    reporter("asin", "operator_mathop", &[("NUM", Number)], Num)
+        .with_fields(&[("OPERATOR", "asin")]),
+    reporter("acos", "operator_mathop", &[("NUM", Number)], Num)
+        .with_fields(&[("OPERATOR", "acos")]),
+    reporter("atan", "operator_mathop", &[("NUM", Number)], Num)
+        .with_fields(&[("OPERATOR", "atan")]),
+    reporter("ln", "operator_mathop", &[("NUM", Number)], Num).with_fields(&[("OPERATOR", "ln")]),
+    reporter("log", "operator_mathop", &[("NUM", Number)], Num).with_fields(&[("OPERATOR", "log")]),
+    reporter("exp", "operator_mathop", &[("NUM", Number)], Num).with_fields(&[("OPERATOR", "e ^")]),
+    reporter("pow10", "operator_mathop", &[("NUM", Number)], Num)
+        .with_fields(&[("OPERATOR", "10 ^")]),
+    // pen
+    stack("pen_clear", "pen_clear", &[]).requires(Extension::Pen),
+    stack("pen_stamp", "pen_stamp", &[]).requires(Extension::Pen),
+    stack("pen_down", "pen_penDown", &[]).requires(Extension::Pen),
+    stack("pen_up", "pen_penUp", &[]).requires(Extension::Pen),
+    stack(
+        "pen_set_color",
+        "pen_setPenColorToColor",
+        &[("COLOR", Color)],
+    )
+    .requires(Extension::Pen),
+    stack(
+        "pen_change_color_param",
+        "pen_changePenColorParamBy",
+        &[
+            ("COLOR_PARAM", Menu(&PEN_COLOR_PARAM_MENU)),
+            ("VALUE", Number),
+        ],
+    )
+    .requires(Extension::Pen),
+    stack("pen_set_size", "pen_setPenSizeTo", &[("SIZE", Number)]).requires(Extension::Pen),
+    stack(
+        "pen_change_size",
+        "pen_changePenSizeBy",
+        &[("SIZE", Number)],
+    )
+    .requires(Extension::Pen),
+    // music
+    stack(
+        "play_drum",
+        "music_playDrumForBeats",
+        &[("DRUM", Menu(&MUSIC_DRUM_MENU)), ("BEATS", Number)],
+    )
+    .requires(Extension::Music),
+    stack("rest", "music_restForBeats", &[("BEATS", Number)]).requires(Extension::Music),
+    stack(
+        "play_note",
+        "music_playNoteForBeats",
+        &[("NOTE", Note), ("BEATS", Number)],
+    )
+    .requires(Extension::Music),
+    stack(
+        "set_instrument",
+        "music_setInstrument",
+        &[("INSTRUMENT", Menu(&MUSIC_INSTRUMENT_MENU))],
+    )
+    .requires(Extension::Music),
+    stack("set_tempo", "music_setTempo", &[("TEMPO", Number)]).requires(Extension::Music),
+    stack("change_tempo", "music_changeTempo", &[("TEMPO", Number)]).requires(Extension::Music),
+    reporter("tempo", "music_getTempo", &[], Num).requires(Extension::Music),
+    // text to speech
+    stack("speak", "text2speech_speakAndWait", &[("WORDS", Text)]).requires(Extension::Text2Speech),
+    stack(
+        "set_voice",
+        "text2speech_setVoice",
+        &[("VOICE", Menu(&TEXT2SPEECH_VOICE_MENU))],
+    )
+    .requires(Extension::Text2Speech),
+    stack(
+        "set_speech_language",
+        "text2speech_setLanguage",
+        &[("LANGUAGE", Menu(&TEXT2SPEECH_LANGUAGE_MENU))],
+    )
+    .requires(Extension::Text2Speech),
+    // translate
+    reporter(
+        "translate",
+        "translate_getTranslate",
+        &[
+            ("WORDS", Text),
+            ("LANGUAGE", Menu(&TRANSLATE_LANGUAGE_MENU)),
+        ],
+        Str,
+    )
+    .requires(Extension::Translate),
+    reporter("viewer_language", "translate_getViewerLanguage", &[], Str)
+        .requires(Extension::Translate),
+    // video sensing
+    hat(
+        "video_motion_greater_than",
+        "videoSensing_whenMotionGreaterThan",
+        &[("REFERENCE", Number)],
+    )
+    .requires(Extension::VideoSensing),
+    reporter(
+        "video",
+        "videoSensing_videoOn",
+        &[
+            ("ATTRIBUTE", Menu(&VIDEO_ATTRIBUTE_MENU)),
+            ("SUBJECT", Menu(&VIDEO_SUBJECT_MENU)),
+        ],
+        Num,
+    )
+    .requires(Extension::VideoSensing),
+    stack(
+        "video_toggle",
+        "videoSensing_videoToggle",
+        &[("VIDEO_STATE", Menu(&VIDEO_STATE_MENU))],
+    )
+    .requires(Extension::VideoSensing),
+    stack(
+        "set_video_transparency",
+        "videoSensing_setVideoTransparency",
+        &[("TRANSPARENCY", Number)],
+    )
+    .requires(Extension::VideoSensing),
+    // makey makey
+    hat(
+        "makey_key_pressed",
+        "makeymakey_whenMakeyKeyPressed",
+        &[("KEY", Menu(&MAKEYMAKEY_KEY_MENU))],
+    )
+    .requires(Extension::Makeymakey),
+    hat(
+        "makey_code_pressed",
+        "makeymakey_whenCodePressed",
+        &[("SEQUENCE", Menu(&MAKEYMAKEY_SEQUENCE_MENU))],
+    )
+    .requires(Extension::Makeymakey),
+    // micro:bit
+    hat(
+        "microbit_button_pressed",
+        "microbit_whenButtonPressed",
+        &[("BTN", Menu(&MICROBIT_BUTTON_MENU))],
+    )
+    .requires(Extension::Microbit),
+    reporter(
+        "microbit_is_button_pressed",
+        "microbit_isButtonPressed",
+        &[("BTN", Menu(&MICROBIT_BUTTON_MENU))],
+        Boolean,
+    )
+    .requires(Extension::Microbit),
+    hat(
+        "microbit_gesture",
+        "microbit_whenGesture",
+        &[("GESTURE", Menu(&MICROBIT_GESTURE_MENU))],
+    )
+    .requires(Extension::Microbit),
+    stack(
+        "microbit_display_text",
+        "microbit_displayText",
+        &[("TEXT", Text)],
+    )
+    .requires(Extension::Microbit),
+    stack("microbit_display_clear", "microbit_displayClear", &[]).requires(Extension::Microbit),
+    hat(
+        "microbit_tilted",
+        "microbit_whenTilted",
+        &[("DIRECTION", Menu(&MICROBIT_TILT_ANY_MENU))],
+    )
+    .requires(Extension::Microbit),
+    reporter(
+        "microbit_is_tilted",
+        "microbit_isTilted",
+        &[("DIRECTION", Menu(&MICROBIT_TILT_ANY_MENU))],
+        Boolean,
+    )
+    .requires(Extension::Microbit),
+    reporter(
+        "microbit_tilt_angle",
+        "microbit_getTiltAngle",
+        &[("DIRECTION", Menu(&MICROBIT_TILT_MENU))],
+        Num,
+    )
+    .requires(Extension::Microbit),
+    hat(
+        "microbit_pin_connected",
+        "microbit_whenPinConnected",
+        &[("PIN", Menu(&MICROBIT_PIN_MENU))],
+    )
+    .requires(Extension::Microbit),
+    // ev3
+    stack(
+        "ev3_motor_clockwise",
+        "ev3_motorTurnClockwise",
+        &[("PORT", Menu(&EV3_MOTOR_PORT_MENU)), ("TIME", Number)],
+    )
+    .requires(Extension::EV3),
+    stack(
+        "ev3_motor_counter_clockwise",
+        "ev3_motorTurnCounterClockwise",
+        &[("PORT", Menu(&EV3_MOTOR_PORT_MENU)), ("TIME", Number)],
+    )
+    .requires(Extension::EV3),
+    stack(
+        "ev3_motor_set_power",
+        "ev3_motorSetPower",
+        &[("PORT", Menu(&EV3_MOTOR_PORT_MENU)), ("POWER", Number)],
+    )
+    .requires(Extension::EV3),
+    reporter(
+        "ev3_motor_position",
+        "ev3_getMotorPosition",
+        &[("PORT", Menu(&EV3_MOTOR_PORT_MENU))],
+        Num,
+    )
+    .requires(Extension::EV3),
+    hat(
+        "ev3_button_pressed",
+        "ev3_whenButtonPressed",
+        &[("PORT", Menu(&EV3_SENSOR_PORT_MENU))],
+    )
+    .requires(Extension::EV3),
+    reporter(
+        "ev3_is_button_pressed",
+        "ev3_buttonPressed",
+        &[("PORT", Menu(&EV3_SENSOR_PORT_MENU))],
+        Boolean,
+    )
+    .requires(Extension::EV3),
+    hat(
+        "ev3_distance_less_than",
+        "ev3_whenDistanceLessThan",
+        &[("DISTANCE", Number)],
+    )
+    .requires(Extension::EV3),
+    hat(
+        "ev3_brightness_less_than",
+        "ev3_whenBrightnessLessThan",
+        &[("DISTANCE", Number)],
+    )
+    .requires(Extension::EV3),
+    reporter("ev3_distance", "ev3_getDistance", &[], Num).requires(Extension::EV3),
+    reporter("ev3_brightness", "ev3_getBrightness", &[], Num).requires(Extension::EV3),
+    stack(
+        "ev3_beep",
+        "ev3_beep",
+        &[("NOTE", Number), ("TIME", Number)],
+    )
+    .requires(Extension::EV3),
+    // boost
+    stack(
+        "boost_motor_on_for",
+        "boost_motorOnFor",
+        &[("MOTOR_ID", Menu(&BOOST_MOTOR_MENU)), ("DURATION", Number)],
+    )
+    .requires(Extension::Boost),
+    stack(
+        "boost_motor_on_for_rotation",
+        "boost_motorOnForRotation",
+        &[("MOTOR_ID", Menu(&BOOST_MOTOR_MENU)), ("ROTATION", Number)],
+    )
+    .requires(Extension::Boost),
+    stack(
+        "boost_motor_on",
+        "boost_motorOn",
+        &[("MOTOR_ID", Menu(&BOOST_MOTOR_MENU))],
+    )
+    .requires(Extension::Boost),
+    stack(
+        "boost_motor_off",
+        "boost_motorOff",
+        &[("MOTOR_ID", Menu(&BOOST_MOTOR_MENU))],
+    )
+    .requires(Extension::Boost),
+    stack(
+        "boost_set_motor_power",
+        "boost_setMotorPower",
+        &[("MOTOR_ID", Menu(&BOOST_MOTOR_MENU)), ("POWER", Number)],
+    )
+    .requires(Extension::Boost),
+    stack(
+        "boost_set_motor_direction",
+        "boost_setMotorDirection",
+        &[
+            ("MOTOR_ID", Menu(&BOOST_MOTOR_MENU)),
+            ("MOTOR_DIRECTION", Menu(&BOOST_MOTOR_DIRECTION_MENU)),
+        ],
+    )
+    .requires(Extension::Boost),
+    reporter(
+        "boost_motor_position",
+        "boost_getMotorPosition",
+        &[("MOTOR_REPORTER_ID", Menu(&BOOST_MOTOR_REPORTER_MENU))],
+        Num,
+    )
+    .requires(Extension::Boost),
+    hat(
+        "boost_color",
+        "boost_whenColor",
+        &[("COLOR", Menu(&BOOST_COLOR_MENU))],
+    )
+    .requires(Extension::Boost),
+    reporter(
+        "boost_seeing_color",
+        "boost_seeingColor",
+        &[("COLOR", Menu(&BOOST_COLOR_MENU))],
+        Boolean,
+    )
+    .requires(Extension::Boost),
+    hat(
+        "boost_tilted",
+        "boost_whenTilted",
+        &[("TILT_DIRECTION_ANY", Menu(&BOOST_TILT_ANY_MENU))],
+    )
+    .requires(Extension::Boost),
+    reporter(
+        "boost_tilt_angle",
+        "boost_getTiltAngle",
+        &[("TILT_DIRECTION", Menu(&BOOST_TILT_MENU))],
+        Num,
+    )
+    .requires(Extension::Boost),
+    stack(
+        "boost_set_light_hue",
+        "boost_setLightHue",
+        &[("HUE", Number)],
+    )
+    .requires(Extension::Boost),
+    // wedo 2.0
+    stack(
+        "wedo_motor_on_for",
+        "wedo2_motorOnFor",
+        &[("MOTOR_ID", Menu(&WEDO2_MOTOR_MENU)), ("DURATION", Number)],
+    )
+    .requires(Extension::Wedo2),
+    stack(
+        "wedo_motor_on",
+        "wedo2_motorOn",
+        &[("MOTOR_ID", Menu(&WEDO2_MOTOR_MENU))],
+    )
+    .requires(Extension::Wedo2),
+    stack(
+        "wedo_motor_off",
+        "wedo2_motorOff",
+        &[("MOTOR_ID", Menu(&WEDO2_MOTOR_MENU))],
+    )
+    .requires(Extension::Wedo2),
+    stack(
+        "wedo_set_motor_power",
+        "wedo2_startMotorPower",
+        &[("MOTOR_ID", Menu(&WEDO2_MOTOR_MENU)), ("POWER", Number)],
+    )
+    .requires(Extension::Wedo2),
+    stack(
+        "wedo_set_motor_direction",
+        "wedo2_setMotorDirection",
+        &[
+            ("MOTOR_ID", Menu(&WEDO2_MOTOR_MENU)),
+            ("MOTOR_DIRECTION", Menu(&WEDO2_MOTOR_DIRECTION_MENU)),
+        ],
+    )
+    .requires(Extension::Wedo2),
+    stack(
+        "wedo_set_light_hue",
+        "wedo2_setLightHue",
+        &[("HUE", Number)],
+    )
+    .requires(Extension::Wedo2),
+    hat(
+        "wedo_distance",
+        "wedo2_whenDistance",
+        &[("OP", Menu(&WEDO2_OP_MENU)), ("REFERENCE", Number)],
+    )
+    .requires(Extension::Wedo2),
+    hat(
+        "wedo_tilted",
+        "wedo2_whenTilted",
+        &[("TILT_DIRECTION_ANY", Menu(&WEDO2_TILT_ANY_MENU))],
+    )
+    .requires(Extension::Wedo2),
+    reporter("wedo_get_distance", "wedo2_getDistance", &[], Num).requires(Extension::Wedo2),
+    reporter(
+        "wedo_is_tilted",
+        "wedo2_isTilted",
+        &[("TILT_DIRECTION_ANY", Menu(&WEDO2_TILT_ANY_MENU))],
+        Boolean,
+    )
+    .requires(Extension::Wedo2),
+    reporter(
+        "wedo_tilt_angle",
+        "wedo2_getTiltAngle",
+        &[("TILT_DIRECTION", Menu(&WEDO2_TILT_MENU))],
+        Num,
+    )
+    .requires(Extension::Wedo2),
+    // force and acceleration
+    hat(
+        "gdxfor_gesture",
+        "gdxfor_whenGesture",
+        &[("GESTURE", Menu(&GDXFOR_GESTURE_MENU))],
+    )
+    .requires(Extension::Gdxfor),
+    hat(
+        "gdxfor_force_pushed_or_pulled",
+        "gdxfor_whenForcePushedOrPulled",
+        &[("CONDITION", Menu(&GDXFOR_PUSH_PULL_MENU))],
+    )
+    .requires(Extension::Gdxfor),
+    reporter("gdxfor_force", "gdxfor_getForce", &[], Num).requires(Extension::Gdxfor),
+    hat(
+        "gdxfor_tilted",
+        "gdxfor_whenTilted",
+        &[("TILT", Menu(&GDXFOR_TILT_ANY_MENU))],
+    )
+    .requires(Extension::Gdxfor),
+    reporter(
+        "gdxfor_is_tilted",
+        "gdxfor_isTilted",
+        &[("TILT", Menu(&GDXFOR_TILT_ANY_MENU))],
+        Boolean,
+    )
+    .requires(Extension::Gdxfor),
+    reporter(
+        "gdxfor_tilt",
+        "gdxfor_getTilt",
+        &[("TILT", Menu(&GDXFOR_TILT_MENU))],
+        Num,
+    )
+    .requires(Extension::Gdxfor),
+    reporter(
+        "gdxfor_is_free_falling",
+        "gdxfor_isFreeFalling",
+        &[],
+        Boolean,
+    )
+    .requires(Extension::Gdxfor),
+    reporter(
+        "gdxfor_spin_speed",
+        "gdxfor_getSpinSpeed",
+        &[("DIRECTION", Menu(&GDXFOR_AXIS_MENU))],
+        Num,
+    )
+    .requires(Extension::Gdxfor),
+    reporter(
+        "gdxfor_acceleration",
+        "gdxfor_getAcceleration",
+        &[("DIRECTION", Menu(&GDXFOR_AXIS_MENU))],
+        Num,
+    )
+    .requires(Extension::Gdxfor),
+];
+
+/// renders a markdown reference of every built-in, grouped by category
+pub fn docs() -> String {
+    let mut docs = String::from("# scuff built-ins\n");
+    let mut category = String::new();
+
+    for block in BLOCKS {
+        let block_category = match &block.extension {
+            Some(extension) => format!("{:?}", extension),
+            None => block.opcode.split('_').next().unwrap().to_string(),
+        };
+
+        if block_category != category {
+            docs.push_str(&format!("\n## {}\n\n", block_category));
+        }
+
+        let args = block
+            .inputs
+            .iter()
+            .map(|(_, input)| match input {
+                Number => "number".to_string(),
+                Text => "string".to_string(),
+                Bool => "bool".to_string(),
+                Color => "\"#rrggbb\"".to_string(),
+                Note => "note".to_string(),
+                Menu(menu) => menu
+                    .options
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<&str>>()
+                    .join(" | "),
+                Field(options) => options
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<&str>>()
+                    .join(" | "),
+            })
+            .map(|arg| {
+                if arg.is_empty() {
+                    "string".to_string()
+                } else {
+                    arg
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        let returns = match &block.shape {
+            Shape::Reporter(return_type) => format!(" -> {:?}", return_type).to_lowercase(),
+            Shape::Hat => " (event)".to_string(),
+            Shape::Stack | Shape::Cap => String::new(),
+        };
+
+        docs.push_str(&format!(
+            "- `{}({}){}`: `{}`\n",
+            block.name, args, returns, block.opcode
+        ));
+
+        category = block_category;
+    }
+
+    docs.push_str("\n## raw blocks\n\n");
+    docs.push_str(
+        "- `block(opcode, {INPUT: value, ...}, {FIELD: \"value\", ...})`: any opcode, \
+         usable as a statement or a value\n",
+    );
+
+    docs
+}
@@ -0,0 +1,210 @@
+use std::path::PathBuf;
+
+pub const USAGE: &str = "usage: scuff <command> [project.toml] [options]
+
+commands:
+    build          compile the project into an sb3 (default)
+    check          compile and validate the project without writing anything
+    emit-json      print the compiled project.json
+    emit-ast       print the syntax tree of every target
+    emit-tokens    print the tokens of every target
+    docs           print the documentation of every built-in block
+    new <name>     create a new project
+    add sprite <name>
+                   add a sprite to the project in the current directory
+
+options:
+    -o, --output <path>    where to write the output (build defaults to project.sb3)
+        --release          deflate the sb3 unless project.toml sets `compression`
+    -q, --quiet            only print errors
+    -v, --verbose          print each step of the build
+        --no-validate      skip checking project.json against the sb3 schema
+    -h, --help             print this message";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Build,
+    Check,
+    EmitJson,
+    EmitAst,
+    EmitTokens,
+    Docs,
+    New(String),
+    AddSprite(String),
+    Help,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cli {
+    pub command: Command,
+    pub project: PathBuf,
+    pub output: Option<PathBuf>,
+    pub verbosity: Verbosity,
+    pub validate: bool,
+    pub release: bool,
+}
+
+impl Cli {
+    /// parses the arguments after the program name
+    pub fn parse(args: &[String]) -> Result<Cli, String> {
+        let mut cli = Cli {
+            command: Command::Build,
+            project: PathBuf::from("project.toml"),
+            output: None,
+            verbosity: Verbosity::Normal,
+            validate: true,
+            release: false,
+        };
+
+        let mut positional: Vec<&str> = Vec::new();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-o" | "--output" => {
+                    let output = args
+                        .next()
+                        .ok_or_else(|| format!("{} expects a path", arg))?;
+                    cli.output = Some(PathBuf::from(output));
+                }
+                "-q" | "--quiet" => cli.verbosity = Verbosity::Quiet,
+                "-v" | "--verbose" => cli.verbosity = Verbosity::Verbose,
+                "--no-validate" => cli.validate = false,
+                "--release" => cli.release = true,
+                "-h" | "--help" => cli.command = Command::Help,
+                _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+                _ => positional.push(arg),
+            }
+        }
+
+        if cli.command == Command::Help {
+            return Ok(cli);
+        }
+
+        let (command, rest) = match positional.split_first() {
+            Some((command, rest)) => (*command, rest),
+            None => ("build", &[][..]),
+        };
+
+        let (command, project) = match (command, rest) {
+            ("build", project) => (Command::Build, project),
+            ("check", project) => (Command::Check, project),
+            ("emit-json", project) => (Command::EmitJson, project),
+            ("emit-ast", project) => (Command::EmitAst, project),
+            ("emit-tokens", project) => (Command::EmitTokens, project),
+            ("docs", []) => (Command::Docs, &[][..]),
+            ("new", [name]) => (Command::New(name.to_string()), &[][..]),
+            ("new", _) => return Err("expected: scuff new <name>".to_string()),
+            ("add", ["sprite", name]) => (Command::AddSprite(name.to_string()), &[][..]),
+            ("add", _) => return Err("expected: scuff add sprite <name>".to_string()),
+            // `scuff path/to/project.toml` builds, like before there were subcommands
+            (project, []) if project.ends_with(".toml") => (Command::Build, &positional[..]),
+            (command, _) => return Err(format!("unknown command: {}", command)),
+        };
+
+        match project {
+            [] => {}
+            [project] => cli.project = PathBuf::from(project),
+            _ => return Err(format!("unexpected argument: {}", project[1])),
+        }
+
+        cli.command = command;
+        Ok(cli)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        Cli::parse(&args)
+    }
+
+    fn exit_code(args: &[&str]) -> i32 {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        crate::exit_code(&args)
+    }
+
+    #[test]
+    fn dispatches_subcommands() {
+        let command = |args: &[&str]| parse(args).unwrap().command;
+
+        assert_eq!(command(&[]), Command::Build);
+        assert_eq!(command(&["build"]), Command::Build);
+        assert_eq!(command(&["check"]), Command::Check);
+        assert_eq!(command(&["emit-json"]), Command::EmitJson);
+        assert_eq!(command(&["emit-ast"]), Command::EmitAst);
+        assert_eq!(command(&["emit-tokens"]), Command::EmitTokens);
+        assert_eq!(command(&["docs"]), Command::Docs);
+        assert_eq!(command(&["new", "game"]), Command::New("game".into()));
+        assert_eq!(
+            command(&["add", "sprite", "player"]),
+            Command::AddSprite("player".into())
+        );
+        assert_eq!(command(&["check", "--help"]), Command::Help);
+    }
+
+    #[test]
+    fn reads_project_paths() {
+        let cli = parse(&["check", "game/project.toml"]).unwrap();
+        assert_eq!(cli.project, PathBuf::from("game/project.toml"));
+
+        let cli = parse(&["game/project.toml"]).unwrap();
+        assert_eq!(cli.command, Command::Build);
+        assert_eq!(cli.project, PathBuf::from("game/project.toml"));
+
+        let cli = parse(&["build"]).unwrap();
+        assert_eq!(cli.project, PathBuf::from("project.toml"));
+    }
+
+    #[test]
+    fn reads_options() {
+        let cli = parse(&["build", "-o", "out.sb3", "--release", "-q"]).unwrap();
+        assert_eq!(cli.output, Some(PathBuf::from("out.sb3")));
+        assert!(cli.release);
+        assert_eq!(cli.verbosity, Verbosity::Quiet);
+        assert!(cli.validate);
+
+        let cli = parse(&["check", "--no-validate", "--verbose"]).unwrap();
+        assert!(!cli.validate);
+        assert_eq!(cli.verbosity, Verbosity::Verbose);
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert_eq!(
+            parse(&["build", "--fast"]),
+            Err("unknown option: --fast".into())
+        );
+        assert_eq!(parse(&["build", "-o"]), Err("-o expects a path".into()));
+        assert_eq!(parse(&["run"]), Err("unknown command: run".into()));
+        assert_eq!(parse(&["new"]), Err("expected: scuff new <name>".into()));
+        assert_eq!(
+            parse(&["add", "costume", "hat"]),
+            Err("expected: scuff add sprite <name>".into())
+        );
+        assert_eq!(
+            parse(&["check", "a.toml", "b.toml"]),
+            Err("unexpected argument: b.toml".into())
+        );
+    }
+
+    #[test]
+    fn exits_with_error_codes() {
+        assert_eq!(exit_code(&["check", "--fast"]), 2);
+        assert_eq!(exit_code(&["check", "tests/missing/project.toml"]), 1);
+        assert_eq!(
+            exit_code(&["check", "tests/hello_world/project.toml", "-q"]),
+            0
+        );
+    }
+}
@@ -9,6 +9,481 @@ test_file!(join_string);
 test_file!(join_variables);
 test_file!(functions);
 test_file!(function_args);
+test_file!(control_blocks);
+test_file!(pen);
+test_file!(music);
+test_file!(builtins);
+test_file!(raw_blocks);
+test_file!(bool_functions);
+test_file!(call_expressions);
+test_file!(warp);
+test_file!(labels);
+test_file!(recursion);
+test_file!(cross_target);
+test_file!(imports);
+test_file!(constants);
+test_file!(optimize);
+test_file!(inline);
+test_file!(costumes);
+test_file!(sprite_properties);
+test_file!(forward_calls);
+test_file!(wait_until);
+test_file!(extensions);
+
+#[test]
+pub fn builds_are_reproducible() {
+    let build = || {
+        let compilation = crate::compile("test_project/project.toml").unwrap();
+        let mut sb3 = std::io::Cursor::new(Vec::new());
+        compilation.write_sb3(&mut sb3).unwrap();
+        sb3.into_inner()
+    };
+
+    assert!(build() == build(), "two builds of test_project differ");
+}
+
+#[test]
+pub fn invalid_project_reports_every_error() {
+    let errors = crate::makefile::MakefileData::parse_from(
+        "tests/invalid_project/project.toml".into(),
+        &crate::files::Disk,
+    )
+    .unwrap_err();
+
+    let errors: Vec<(&str, Option<usize>)> = errors
+        .iter()
+        .map(|error| (error.key.as_str(), error.line))
+        .collect();
+
+    assert_eq!(
+        errors,
+        vec![
+            ("extensions[1]", Some(2)),
+            ("stage[1]", Some(12)),
+            ("stage[0].backdrops", Some(7)),
+            ("stage[0].text_to_speech_language", Some(9)),
+            ("stage[1].name", Some(12)),
+            ("sprite[0].size", Some(22)),
+            ("sprite[0].script", Some(19)),
+            ("sprite[0].costumes[0].path", Some(20)),
+            ("sprite[0].costumes[1].path", Some(20)),
+            ("sprite[1].name", Some(26)),
+            ("sprite[1].layer_order", Some(30)),
+            ("sprite[1].costumes[0].bitmap_resolution", Some(28)),
+            ("sprite[2].name", Some(33)),
+            ("sprite[2].costumes", Some(35)),
+        ]
+    );
+}
+
+#[test]
+pub fn new_project_compiles() {
+    let path = std::env::temp_dir().join(format!("scuff_new_project_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+
+    crate::scaffold::new_project(&path).unwrap();
+    crate::scaffold::add_sprite(&path, "player").unwrap();
+    assert!(crate::scaffold::add_sprite(&path, "player").is_err());
+
+    let project = crate::compile(path.join("project.toml")).unwrap().project;
+    std::fs::remove_dir_all(&path).unwrap();
+
+    let names: Vec<&str> = project
+        .targets
+        .iter()
+        .map(|target| target.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["Stage", "sprite1", "player"]);
+    assert_eq!(
+        project.targets[0].costumes[0].asset_id,
+        crate::project::Costume::default().asset_id
+    );
+}
+
+/// loads `files` from tests/`test_name` into memory, keeping their relative paths
+fn memory_project(test_name: &str, files: &[&str]) -> crate::files::MemoryFiles {
+    let mut memory = crate::files::MemoryFiles::new();
+
+    for file in files {
+        memory.insert(
+            file,
+            std::fs::read(format!("tests/{}/{}", test_name, file)).unwrap(),
+        );
+    }
+
+    memory
+}
+
+#[test]
+pub fn compiles_from_memory() {
+    let mut files = memory_project(
+        "imports",
+        &[
+            "project.toml",
+            "stage1.scuff",
+            "sprite1.scuff",
+            "backdrop1.svg",
+            "costume1.svg",
+            "lib/math.scuff",
+            "lib/util.scuff",
+        ],
+    );
+
+    let from_memory = crate::compile_from("project.toml", &files).unwrap();
+    let from_disk = crate::compile("tests/imports/project.toml").unwrap();
+    assert_json_eq!(
+        serde_json::to_value(&from_memory.project).unwrap(),
+        serde_json::to_value(&from_disk.project).unwrap()
+    );
+
+    files.remove("lib/math.scuff");
+    let errors = crate::compile_from("project.toml", &files).unwrap_err();
+    assert!(errors[0].message.contains("lib/math.scuff"), "{:?}", errors);
+
+    files.remove("costume1.svg");
+    let errors = crate::compile_from("project.toml", &files).unwrap_err();
+    assert_eq!(errors[0].path, Some("project.toml".into()));
+    assert_eq!(errors[0].line, Some(13));
+}
+
+#[test]
+pub fn pen_requires_extension() {
+    let mut files = memory_project(
+        "pen",
+        &[
+            "stage1.scuff",
+            "sprite1.scuff",
+            "backdrop1.svg",
+            "costume1.svg",
+        ],
+    );
+
+    let makefile = read_to_string("tests/pen/project.toml").unwrap();
+    files.insert(
+        "project.toml",
+        makefile.replace(r#"extensions = ["Pen"]"#, "extensions = []"),
+    );
+
+    let errors = crate::compile_from("project.toml", &files).unwrap_err();
+    assert_eq!(
+        errors[0].message,
+        "pen_clear requires the Pen extension, add it to `extensions` in project.toml"
+    );
+}
+
+#[test]
+pub fn label_rejects_table_arguments() {
+    let mut files = memory_project(
+        "labels",
+        &[
+            "project.toml",
+            "stage1.scuff",
+            "backdrop1.svg",
+            "costume1.svg",
+        ],
+    );
+    files.insert(
+        "sprite1.scuff",
+        "#[label(\"sort %items\")]\nfunction sort(items: table) -> void {\n\tsay(\"sorted\");\n}\n",
+    );
+
+    let errors = crate::compile_from("project.toml", &files).unwrap_err();
+    assert_eq!(
+        errors[0].message,
+        "argument %items of type table cannot appear in the label of sort"
+    );
+}
+
+#[test]
+pub fn raw_block_conditions_reject_calls() {
+    let mut files = memory_project(
+        "wait_until",
+        &[
+            "project.toml",
+            "stage1.scuff",
+            "backdrop1.svg",
+            "costume1.svg",
+        ],
+    );
+    files.insert(
+        "sprite1.scuff",
+        "function is_small(n: number) -> bool {\n\treturn (n == 1);\n}\n\n\
+         event flag_clicked {\n\tblock(\"control_wait_until\", {CONDITION: is_small(1)});\n}\n",
+    );
+
+    let errors = crate::compile_from("project.toml", &files).unwrap_err();
+    assert_eq!(
+        errors[0].message,
+        "input CONDITION of a raw block cannot call a function, scratch may check it more than once"
+    );
+}
+
+#[test]
+pub fn recursive_functions_keep_to_one_script() {
+    let mut files = memory_project(
+        "recursion",
+        &[
+            "project.toml",
+            "stage1.scuff",
+            "sprite1.scuff",
+            "backdrop1.svg",
+            "costume1.svg",
+        ],
+    );
+    assert!(crate::compile_from("project.toml", &files).is_ok());
+
+    let script = read_to_string("tests/recursion/sprite1.scuff").unwrap();
+    files.insert(
+        "sprite1.scuff",
+        format!(
+            "{}\nevent key_pressed(space) {{\n\tsay(sum_to(2));\n}}\n",
+            script
+        ),
+    );
+
+    let errors = crate::compile_from("project.toml", &files).unwrap_err();
+    assert_eq!(
+        errors[0].message,
+        "recursive function sum_to is called from more than one script, which would share its stack"
+    );
+}
+
+#[test]
+pub fn clones_skip_remote_calls() {
+    let mut files = memory_project(
+        "cross_target",
+        &["project.toml", "backdrop1.svg", "costume1.svg"],
+    );
+    files.insert(
+        "stage1.scuff",
+        "event flag_clicked {\n\tsprite1::greet();\n}\n",
+    );
+    files.insert(
+        "sprite1.scuff",
+        "function greet() -> void {\n\tsay(\"hi\");\n}\n",
+    );
+
+    let compilation = crate::compile_from("project.toml", &files).unwrap();
+    compilation.validate().unwrap();
+
+    let sprite = &compilation.project.targets[1];
+    let opcode_of = |id: &Option<String>| sprite.blocks[id.as_ref().unwrap()].opcode.as_str();
+    let receiver = sprite
+        .blocks
+        .values()
+        .find(|block| block.opcode == "event_whenbroadcastreceived")
+        .unwrap();
+    assert_eq!(opcode_of(&receiver.next), "control_if");
+
+    let clone_hat = sprite
+        .blocks
+        .values()
+        .find(|block| block.opcode == "control_start_as_clone")
+        .unwrap();
+    assert_eq!(opcode_of(&clone_hat.next), "data_setvariableto");
+}
+
+#[test]
+pub fn errors_report_their_file() {
+    let mut files = memory_project(
+        "hello_world",
+        &[
+            "project.toml",
+            "stage1.scuff",
+            "backdrop1.svg",
+            "costume1.svg",
+        ],
+    );
+
+    files.insert("sprite1.scuff", "event flag_clicked {\n\tsay(\"hi\";\n}\n");
+    let errors = crate::compile_from("project.toml", &files).unwrap_err();
+    assert_eq!(errors[0].path, Some("sprite1.scuff".into()));
+    assert_eq!(errors[0].line, Some(2));
+
+    files.insert("sprite1.scuff", "event flag_clicked {\n\tmissing = 1;\n}\n");
+    let errors = crate::compile_from("project.toml", &files).unwrap_err();
+    assert_eq!(errors[0].path, Some("sprite1.scuff".into()));
+    assert_eq!(errors[0].message, "variable \"missing\" not found in scope");
+}
+
+#[test]
+pub fn key_pressed_events() {
+    let mut files = memory_project(
+        "hello_world",
+        &[
+            "project.toml",
+            "stage1.scuff",
+            "backdrop1.svg",
+            "costume1.svg",
+        ],
+    );
+    files.insert(
+        "sprite1.scuff",
+        "event key_pressed(space) {\n\tsay(\"space\");\n}\n\nevent key_pressed(7) {}\n",
+    );
+
+    let project = crate::compile_from("project.toml", &files).unwrap().project;
+    let mut keys: Vec<&Value> = project.targets[1]
+        .blocks
+        .values()
+        .filter(|block| block.opcode == "event_whenkeypressed")
+        .map(|block| &block.fields.as_ref().unwrap()["KEY_OPTION"][0])
+        .collect();
+    keys.sort_by_key(|key| key.to_string());
+    assert_eq!(keys, vec!["7", "space"]);
+}
+
+#[test]
+pub fn optimize_keeps_labelled_functions() {
+    let project = crate::compile("tests/optimize/project.toml")
+        .unwrap()
+        .project;
+
+    let proc_codes: Vec<&str> = project.targets[1]
+        .blocks
+        .values()
+        .filter(|block| block.opcode == "procedures_prototype")
+        .filter_map(|block| block.mutation.as_ref()?.proccode.as_deref())
+        .collect();
+    assert_eq!(proc_codes, vec!["shout %s"]);
+}
+
+#[test]
+pub fn compression_defaults_to_stored() {
+    use crate::makefile::Compression;
+
+    // (debug build, release build)
+    let compression = |extra: &str| {
+        let mut files = memory_project(
+            "hello_world",
+            &[
+                "stage1.scuff",
+                "sprite1.scuff",
+                "backdrop1.svg",
+                "costume1.svg",
+            ],
+        );
+        let makefile = read_to_string("tests/hello_world/project.toml").unwrap();
+        files.insert("project.toml", format!("{}\n{}", extra, makefile));
+
+        let mut compilation = crate::compile_from("project.toml", &files).unwrap();
+        let debug = compilation.options.compression.unwrap_or_default();
+        compilation.release();
+        (debug, compilation.options.compression.unwrap_or_default())
+    };
+
+    assert_eq!(compression(""), (Compression::Stored, Compression::Deflate));
+    assert_eq!(
+        compression("compression = \"Stored\""),
+        (Compression::Stored, Compression::Stored)
+    );
+    assert_eq!(
+        compression("compression_level = 3"),
+        (Compression::Deflate, Compression::Deflate)
+    );
+}
+
+#[test]
+pub fn failed_packaging_leaves_no_partial_sb3() {
+    use crate::packager::PackageError;
+
+    let compilation = crate::compile("tests/hello_world/project.toml").unwrap();
+    let dir = std::env::temp_dir().join(format!("scuff_failed_package_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    // the sb3 is written in full, but can't replace a directory
+    let output = dir.join("project.sb3");
+    std::fs::create_dir(&output).unwrap();
+    std::fs::write(output.join("keep"), "").unwrap();
+
+    let error = compilation.package(&output).unwrap_err();
+    assert!(
+        matches!(&error, PackageError::Io(path, _) if *path == output),
+        "{:?}",
+        error
+    );
+    assert!(output.join("keep").exists());
+
+    // the output can't even be created
+    let missing = dir.join("missing").join("project.sb3");
+    let error = compilation.package(&missing).unwrap_err();
+    assert!(matches!(error, PackageError::Io(..)), "{:?}", error);
+
+    let mut left_over: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    left_over.sort();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(left_over, vec!["project.sb3"]);
+}
+
+#[test]
+pub fn duplicate_costumes_are_stored_once() {
+    let mut files = memory_project(
+        "hello_world",
+        &[
+            "stage1.scuff",
+            "sprite1.scuff",
+            "backdrop1.svg",
+            "costume1.svg",
+        ],
+    );
+    files.insert(
+        "copy.svg",
+        read_to_string("tests/hello_world/costume1.svg").unwrap(),
+    );
+
+    let makefile = read_to_string("tests/hello_world/project.toml").unwrap();
+    files.insert(
+        "project.toml",
+        makefile.replace(
+            r#"costumes = [{ name = "costume1", path = "costume1.svg" }]"#,
+            r#"costumes = [
+    { name = "costume1", path = "costume1.svg" },
+    { name = "copy", path = "copy.svg" },
+    { name = "again", path = "costume1.svg" },
+]"#,
+        ),
+    );
+
+    let compilation = crate::compile_from("project.toml", &files).unwrap();
+    let md5exts: Vec<&str> = compilation
+        .project
+        .targets
+        .iter()
+        .flat_map(|target| &target.costumes)
+        .map(|costume| costume.md5ext.as_str())
+        .collect();
+
+    assert_eq!(md5exts.len(), 4);
+    assert!(
+        md5exts.iter().all(|md5ext| *md5ext == md5exts[0]),
+        "{:?}",
+        md5exts
+    );
+    assert_eq!(compilation.assets.iter().count(), 1);
+}
+
+#[test]
+pub fn maps_are_written_in_sorted_order() {
+    let mut target = crate::project::Target::default();
+    for name in ["c", "a", "d", "b"] {
+        target
+            .broadcasts
+            .insert(format!("broadcast_{}", name), name.to_string());
+    }
+
+    let json = serde_json::to_string(&target).unwrap();
+    assert!(
+        json.contains(
+            r#""broadcasts":{"broadcast_a":"a","broadcast_b":"b","broadcast_c":"c","broadcast_d":"d"}"#
+        ),
+        "broadcasts are not sorted in {}",
+        json
+    );
+}
 
 #[macro_export]
 macro_rules! test_file {
@@ -17,7 +492,7 @@ macro_rules! test_file {
             #[test]
             pub fn [<$test_name _matches>]() {
                 let path = format!("tests/{}/project.toml", stringify!($test_name));
-                let (project, _) = crate::compile_project(path);
+                let project = crate::compile(path).unwrap().project;
 
                 let actual = serde_json::to_value(&project).unwrap();
 
@@ -32,8 +507,7 @@ macro_rules! test_file {
             #[test]
             pub fn [<$test_name _schema>]() {
                 let path = format!("tests/{}/project.toml", stringify!($test_name));
-                let (project, _) = crate::compile_project(path);
-                crate::validate_project(&project);
+                crate::compile(path).unwrap().validate().unwrap();
             }
         }
     };
@@ -1,30 +1,65 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::default;
 
-use md5::{Digest, Md5};
 use serde_json::{json, Value};
 
-use crate::makefile::TargetData;
-use crate::parser::{Event, Expr, Stmt};
+use crate::assets::AssetStore;
+use crate::blocks::{self, BlockDef, Input, Menu, Shape};
+use crate::error::{bail, Result};
+use crate::image;
+use crate::makefile::{AssetData, Extension, TargetData, TargetProperties};
+use crate::optimize;
+use crate::parser::{Attribute, Event, Expr, Key, Stmt};
 use crate::project::{Block, Costume, Mutation, Project, Target};
 use crate::token::{Operator, Type};
 
+/// ```text
+/// (QualifiedName, BroadcastId, Vec<(ParamVarName, ParamVarId, ParamType)>, ReturnVarId, ReturnType)
+/// ```
+type RemoteFunction = (String, String, Vec<(String, String, Type)>, String, Type);
+
+/// (Arguments, Body)
+type InlineFunction = (Vec<(String, Type)>, Vec<Stmt>);
+
 pub struct Compiler {
     project: Project,
     targets: Vec<(TargetData, Vec<Stmt>)>,
+    /// extensions declared in project.toml
+    extensions: Vec<Extension>,
     current_target: (TargetData, Vec<Stmt>),
     next_block_id: Option<String>,
     block_id: usize,
     /// maps variables to their scope
-    /// ```
+    /// ```text
     /// HashMap<ScopePath, HashMap<VarName, (VarId, VarType)>>
     var_table: HashMap<Vec<String>, HashMap<String, (String, Type)>>,
     var_id: usize,
-    /// ```
+    /// ```text
     /// let function_table = arg_table.get(function_name)?;
-    /// let (arg_id, arg_name) = function_table[arg_position]?;
-    /// HashMap<FunctionName, Vec<(ArgId, ArgName)>>
-    arg_table: HashMap<String, Vec<(String, String)>>,
+    /// let (arg_id, arg_name, arg_type) = function_table[arg_position]?;
+    /// HashMap<FunctionName, Vec<(ArgId, ArgName, ArgType)>>
+    arg_table: HashMap<String, Vec<(String, String, Type)>>,
+    /// HashMap<FunctionName, ReturnType>
+    return_table: HashMap<String, Type>,
+    /// ```text
+    /// let (proc_code, arg_order, warp) = prototype_table.get(function_name)?;
+    /// HashMap<FunctionName, (ProcCode, Vec<ArgPosition>, Warp)>
+    /// ```
+    /// arg_order lists the arguments in the order they appear in the proccode
+    prototype_table: HashMap<String, (String, Vec<usize>, bool)>,
+    /// the function whose body is currently being compiled
+    current_function: Option<String>,
+    /// (FrameSize, NextSlot) while compiling a `#[recursive]` function
+    stack_frame: Option<(usize, usize)>,
+    /// maps the locals of `#[recursive]` functions to their slot in the stack frame
+    /// HashMap<VarId, Slot>
+    stack_slots: HashMap<String, usize>,
+    /// id of the current target's stack list
+    stack_list: Option<String>,
+    /// functions called from another target, e.g. `Stage::reset_board()`
+    remote_functions: Vec<RemoteFunction>,
+    inline_functions: HashMap<String, InlineFunction>,
+    temp_id: usize,
     arg_id: usize,
     target_index: usize,
     parent: Option<usize>,
@@ -34,14 +69,24 @@ pub struct Compiler {
 
 // TODO: fix excessive cloning
 impl Compiler {
-    pub fn new(targets: Vec<(TargetData, Vec<Stmt>)>) -> Compiler {
+    pub fn new(targets: Vec<(TargetData, Vec<Stmt>)>, extensions: Vec<Extension>) -> Compiler {
         Compiler {
             targets: targets.clone(),
+            extensions,
             current_target: targets[0].clone(),
             project: Project::new(),
             block_id: 0,
             arg_id: 0,
             arg_table: HashMap::new(),
+            return_table: HashMap::new(),
+            prototype_table: HashMap::new(),
+            current_function: None,
+            stack_frame: None,
+            stack_slots: HashMap::new(),
+            stack_list: None,
+            remote_functions: Vec::new(),
+            inline_functions: HashMap::new(),
+            temp_id: 0,
             var_id: 0,
             target_index: 0,
             parent: None,
@@ -51,55 +96,427 @@ impl Compiler {
         }
     }
 
-    pub fn compile(&mut self) -> &Project {
+    pub fn compile(&mut self, assets: &AssetStore) -> Result<&Project> {
+        self.project.extensions = self
+            .extensions
+            .iter()
+            .map(|extension| extension.id().to_string())
+            .collect();
+
+        self.collect_remote_functions()?;
+
         for target in self.targets.clone() {
-            self.current_target = target.clone();
-            self.block_id = 0;
-            self.parent = None;
-
-            self.project.targets.push(Target {
-                is_stage: self.current_target.0.is_stage,
-                name: self.current_target.0.name.clone(),
-                ..Target::default()
+            self.compile_target(target.clone(), assets)
+                .map_err(|error| error.in_file(&target.0.script_path))?;
+            self.target_index += 1;
+        }
+
+        // arguments and return values are passed through stage variables, which every target can see
+        let stage = self
+            .project
+            .targets
+            .iter_mut()
+            .find(|target| target.is_stage)
+            .unwrap();
+
+        for (qualified_name, broadcast_id, params, return_var_id, return_type) in
+            &self.remote_functions
+        {
+            stage
+                .broadcasts
+                .insert(broadcast_id.clone(), qualified_name.clone());
+
+            for (param_name, param_id, _) in params {
+                stage
+                    .variables
+                    .insert(param_id.clone(), json!([param_name, 0]));
+            }
+
+            if *return_type != Type::Void {
+                stage.variables.insert(
+                    return_var_id.clone(),
+                    json!([format!("!func_var_{}", qualified_name), 0]),
+                );
+            }
+        }
+
+        //
+        Ok(&self.project)
+    }
+
+    fn compile_target(
+        &mut self,
+        target: (TargetData, Vec<Stmt>),
+        assets: &AssetStore,
+    ) -> Result<()> {
+        self.current_target = target;
+        self.block_id = 0;
+        self.parent = None;
+        self.stack_list = None;
+
+        // block ids (and so scopes) and function names are only unique within a target
+        self.var_table = HashMap::new();
+        self.arg_table = HashMap::new();
+        self.return_table = HashMap::new();
+        self.prototype_table = HashMap::new();
+        self.inline_functions = HashMap::new();
+        self.declare_remote_functions();
+
+        let target = Compiler::empty_target(&self.current_target.0);
+        self.project.targets.push(target);
+
+        for costume in &self.current_target.0.costumes {
+            let costume = Compiler::compile_costume(costume, assets)?;
+            self.project.targets[self.target_index]
+                .costumes
+                .push(costume);
+        }
+
+        let ast = &self.current_target.1.clone();
+        self.declare_functions(ast)?;
+        self.check_shared_stacks(ast)?;
+
+        for statement in ast {
+            self.compile_top_level_statement(statement)?;
+        }
+
+        let receivers: Vec<RemoteFunction> = self
+            .remote_functions
+            .iter()
+            .filter(|function| function.0.split_once("::").unwrap().0 == self.current_target.0.name)
+            .cloned()
+            .collect();
+
+        if !receivers.is_empty() && !self.current_target.0.is_stage {
+            self.compile_clone_flag()?;
+        }
+
+        for remote_function in receivers {
+            self.compile_remote_receiver(remote_function)?;
+        }
+
+        Ok(())
+    }
+
+    /// finds every call to a function in another target and allocates the broadcast
+    /// and stage variables used to pass its arguments and return value
+    fn collect_remote_functions(&mut self) -> Result<()> {
+        let mut qualified_names = Vec::new();
+        for (_, ast) in &self.targets {
+            Compiler::find_remote_calls(ast, &mut qualified_names);
+        }
+
+        for qualified_name in qualified_names {
+            let (target_name, func_name) = qualified_name.split_once("::").unwrap();
+
+            let Some((_, ast)) = self
+                .targets
+                .iter()
+                .find(|(target, _)| target.name == target_name)
+            else {
+                bail!(
+                    "target {} does not exist, called as: {}",
+                    target_name,
+                    qualified_name
+                );
+            };
+
+            let Some((args, return_type)) = ast.iter().find_map(|stmt| match stmt {
+                Stmt::FunctionDeclaration(name, args, _, return_type, _) if name == func_name => {
+                    Some((args.clone(), return_type.clone()))
+                }
+                _ => None,
+            }) else {
+                bail!(
+                    "function {} does not exist in target {}",
+                    func_name,
+                    target_name
+                );
+            };
+
+            let params = args
+                .into_iter()
+                .map(|(arg_name, arg_type)| {
+                    (
+                        format!("!{}_{}", qualified_name, arg_name),
+                        self.gen_var_id(),
+                        arg_type,
+                    )
+                })
+                .collect();
+
+            let return_var_id = self.gen_var_id();
+            let broadcast_id = format!("broadcast_{}", self.remote_functions.len() + 1);
+
+            self.remote_functions.push((
+                qualified_name,
+                broadcast_id,
+                params,
+                return_var_id,
+                return_type,
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn find_remote_calls(body: &[Stmt], qualified_names: &mut Vec<String>) {
+        for stmt in body {
+            match stmt {
+                Stmt::FunctionDeclaration(_, _, body, _, _)
+                | Stmt::EventHandler(_, body)
+                | Stmt::While(_, body) => Compiler::find_remote_calls(body, qualified_names),
+                Stmt::If(_, body_true, body_false) => {
+                    Compiler::find_remote_calls(body_true, qualified_names);
+                    if let Some(body_false) = body_false {
+                        Compiler::find_remote_calls(body_false, qualified_names);
+                    }
+                }
+                _ => {}
+            }
+
+            Compiler::map_stmt_exprs(stmt, &mut |expr| {
+                Compiler::find_remote_calls_in_expr(expr, qualified_names);
+                expr.clone()
             });
 
-            for costume in &self.current_target.0.costumes {
-                let mut hasher = Md5::new();
-                hasher.update(&costume.content);
-                let hash = format!("{:x}", hasher.finalize());
+            if let Stmt::FunctionCall(func_name, _) = stmt {
+                if func_name.contains("::") && !qualified_names.contains(func_name) {
+                    qualified_names.push(func_name.clone());
+                }
+            }
+        }
+    }
 
-                // FIXME: what the fuck, rust moment
-                let extension = costume
-                    .path
-                    .extension()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_string();
+    fn find_remote_calls_in_expr(expr: &Expr, qualified_names: &mut Vec<String>) {
+        match expr {
+            Expr::FunctionCall(func_name, args) => {
+                for arg in args {
+                    Compiler::find_remote_calls_in_expr(arg, qualified_names);
+                }
 
-                self.project.targets[self.target_index]
-                    .costumes
-                    .push(Costume {
-                        name: costume.name.clone(),
-                        data_format: extension.clone(),
-                        asset_id: hash.clone().into(),
-                        md5ext: format!("{}.{}", hash, extension),
-                        rotation_center_x: None,
-                        rotation_center_y: None,
-                    })
+                if func_name.contains("::") && !qualified_names.contains(func_name) {
+                    qualified_names.push(func_name.clone());
+                }
             }
+            Expr::Binary(left, _, right) => {
+                Compiler::find_remote_calls_in_expr(left, qualified_names);
+                Compiler::find_remote_calls_in_expr(right, qualified_names);
+            }
+            Expr::Map(entries) => {
+                for (_, value) in entries {
+                    Compiler::find_remote_calls_in_expr(value, qualified_names);
+                }
+            }
+            _ => {}
+        }
+    }
 
-            let ast = &self.current_target.1.clone();
+    /// makes the stage variables of remote functions visible to the current target
+    fn declare_remote_functions(&mut self) {
+        let global_scope = self.var_table.entry(Vec::new()).or_default();
 
-            for statement in ast {
-                self.compile_top_level_statement(statement);
+        for (qualified_name, _, params, return_var_id, return_type) in &self.remote_functions {
+            for (param_name, param_id, param_type) in params {
+                global_scope.insert(param_name.clone(), (param_id.clone(), param_type.clone()));
             }
 
-            self.target_index += 1;
+            if *return_type != Type::Void {
+                global_scope.insert(
+                    format!("!func_var_{}", qualified_name),
+                    (return_var_id.clone(), return_type.clone()),
+                );
+            }
+
+            self.return_table
+                .insert(qualified_name.clone(), return_type.clone());
         }
+    }
 
-        //
-        &self.project
+    /// sprite-local `!is_clone`, set by `when I start as a clone`,
+    /// so only the original sprite answers remote calls
+    fn compile_clone_flag(&mut self) -> Result<()> {
+        self.push_var(Vec::new(), "!is_clone".into(), Type::Number);
+
+        self.compile_top_level_statement(&Stmt::EventHandler(
+            Event::Hat("start_as_clone".into(), Vec::new()),
+            vec![Stmt::VariableAssignment(
+                "!is_clone".into(),
+                Expr::Number(1.0),
+            )],
+        ))
+    }
+
+    /// `when I receive [Target::function]`, calls the function with the arguments
+    /// from its stage variables and copies its return value back,
+    /// every clone of a sprite receives the broadcast too, so they skip it
+    fn compile_remote_receiver(
+        &mut self,
+        (qualified_name, broadcast_id, params, _, return_type): RemoteFunction,
+    ) -> Result<()> {
+        let func_name = qualified_name.split_once("::").unwrap().1;
+
+        let mut body = vec![Stmt::FunctionCall(
+            func_name.to_string(),
+            params
+                .iter()
+                .map(|(param_name, _, _)| Expr::Identifier(param_name.clone()))
+                .collect(),
+        )];
+
+        if return_type != Type::Void {
+            body.push(Stmt::VariableAssignment(
+                format!("!func_var_{}", qualified_name),
+                Expr::Identifier(format!("!func_var_{}", func_name)),
+            ));
+        }
+
+        if !self.current_target.0.is_stage {
+            body = vec![Stmt::If(
+                Expr::Binary(
+                    Box::new(Expr::Identifier("!is_clone".into())),
+                    Operator::EqualEqual,
+                    Box::new(Expr::Number(0.0)),
+                ),
+                body,
+                None,
+            )];
+        }
+
+        let hat_id = self.gen_block_id();
+
+        self.push_block(
+            &Block {
+                opcode: "event_whenbroadcastreceived".to_string(),
+                fields: Some(json!({"BROADCAST_OPTION": [qualified_name, broadcast_id]})),
+                next: Some(self.peek_next_block_id()),
+                top_level: Some(true),
+                ..Block::default()
+            },
+            hat_id.clone(),
+        );
+
+        self.compile_body_statements(&body, hat_id, None)
+    }
+
+    /// a target with everything but its blocks, variables and costumes filled in
+    fn empty_target(target: &TargetData) -> Target {
+        let target_base = Target {
+            is_stage: target.is_stage,
+            name: target.name.clone(),
+            current_costume: Some(target.current_costume as i32),
+            ..Target::default()
+        };
+
+        match &target.properties {
+            TargetProperties::Stage {
+                tempo,
+                video_state,
+                video_transparency,
+                text_to_speech_language,
+            } => Target {
+                layer_order: Some(0),
+                tempo: Some(*tempo),
+                video_state: Some(video_state.id().to_string()),
+                video_transparency: Some(*video_transparency),
+                text_to_speech_language: text_to_speech_language.clone(),
+                ..target_base
+            },
+            TargetProperties::Sprite {
+                x,
+                y,
+                size,
+                direction,
+                visible,
+                draggable,
+                rotation_style,
+                layer_order,
+            } => Target {
+                layer_order: Some(*layer_order as i32),
+                x: Some(*x),
+                y: Some(*y),
+                size: Some(*size),
+                direction: Some(*direction),
+                visible: Some(*visible),
+                draggable: Some(*draggable),
+                rotation_style: Some(rotation_style.id().to_string()),
+                ..target_base
+            },
+        }
+    }
+
+    fn compile_costume(costume: &AssetData, assets: &AssetStore) -> Result<Costume> {
+        let asset = assets.get(&costume.path);
+        let extension = &asset.data_format;
+
+        if !image::COSTUME_FORMATS.contains(&extension.as_str()) {
+            bail!(
+                "costume {} ({}) has unsupported format {:?}, expected one of: {}",
+                costume.name,
+                costume.path.display(),
+                extension,
+                image::COSTUME_FORMATS.join(", ")
+            );
+        }
+
+        let bitmap_resolution = if image::is_bitmap(extension) {
+            Some(costume.bitmap_resolution.unwrap_or(1))
+        } else if costume.bitmap_resolution.is_some() {
+            bail!(
+                "costume {} ({}) is a vector, bitmap_resolution only applies to bitmaps",
+                costume.name,
+                costume.path.display()
+            );
+        } else {
+            None
+        };
+
+        // scratch measures the rotation center from the top left of the image
+        let center = costume.center.or_else(|| {
+            image::image_size(extension, &asset.content)
+                .map(|(width, height)| (width / 2.0, height / 2.0))
+        });
+
+        Ok(Costume {
+            name: costume.name.clone(),
+            data_format: extension.clone(),
+            asset_id: asset.asset_id.clone(),
+            md5ext: asset.md5ext.clone(),
+            bitmap_resolution,
+            rotation_center_x: center.map(|(x, _)| x),
+            rotation_center_y: center.map(|(_, y)| y),
+        })
+    }
+
+    /// sets the parameter variables of a call to another target, then broadcasts and waits
+    fn expand_remote_call(&self, stmt: Stmt) -> Result<Vec<Stmt>> {
+        let (func_name, args) = match &stmt {
+            Stmt::FunctionCall(func_name, args) if func_name.contains("::") => (func_name, args),
+            _ => return Ok(vec![stmt]),
+        };
+
+        let (_, _, params, _, _) = self
+            .remote_functions
+            .iter()
+            .find(|(qualified_name, _, _, _, _)| qualified_name == func_name)
+            .unwrap();
+
+        self.assert_arg_count(func_name, args, params.len())?;
+
+        let mut stmts: Vec<Stmt> = params
+            .iter()
+            .zip(args)
+            .map(|((param_name, _, _), arg)| {
+                Stmt::VariableAssignment(param_name.clone(), arg.clone())
+            })
+            .collect();
+
+        stmts.push(Stmt::FunctionCall(
+            "!broadcast_and_wait".to_string(),
+            vec![Expr::String(func_name.clone())],
+        ));
+
+        Ok(stmts)
     }
 
     // TODO: actual type checking
@@ -112,46 +529,149 @@ impl Compiler {
         }
     }
 
-    fn compile_condition(&mut self, condition: &Expr, parent_id: String, current_id: String) {
+    fn compile_condition(
+        &mut self,
+        condition: &Expr,
+        parent_id: String,
+        current_id: String,
+    ) -> Result<()> {
+        match condition {
+            // scratch has no boolean literals, an empty `not` reports true and an empty `and` reports false
+            Expr::Bool(value) => {
+                let opcode = if *value {
+                    "operator_not"
+                } else {
+                    "operator_and"
+                };
+
+                self.push_block(
+                    &Block {
+                        opcode: opcode.to_string(),
+                        parent: Some(parent_id),
+                        inputs: Some(HashMap::new()),
+                        shadow: Some(false),
+                        top_level: Some(false),
+                        ..Default::default()
+                    },
+                    current_id,
+                );
+                return Ok(());
+            }
+            Expr::Identifier(ident) => {
+                if self.var_exists(self.scope_path.clone(), ident.clone()) {
+                    // variables store bools as "true" or "false"
+                    self.compile_bool_from_var(ident.clone(), condition, parent_id, current_id)?;
+                } else if self.arg_type(ident) == Some(Type::Bool) {
+                    self.push_block(
+                        &Block {
+                            opcode: "argument_reporter_boolean".to_string(),
+                            parent: Some(parent_id),
+                            fields: Some(json!({"VALUE": [ident, Value::Null]})),
+                            shadow: Some(false),
+                            top_level: Some(false),
+                            ..Default::default()
+                        },
+                        current_id,
+                    );
+                } else {
+                    bail!("expected bool in condition, recieved: {:?}", condition);
+                }
+                return Ok(());
+            }
+            // raw blocks can't be type checked, trust that the opcode reports a bool
+            Expr::FunctionCall(func_name, args) if func_name == "block" => {
+                let (opcode, inputs, fields) = self.compile_raw_block(args, current_id.clone())?;
+
+                self.push_block(
+                    &Block {
+                        opcode,
+                        parent: Some(parent_id),
+                        inputs: Some(inputs),
+                        fields,
+                        shadow: Some(false),
+                        top_level: Some(false),
+                        ..Block::default()
+                    },
+                    current_id,
+                );
+                return Ok(());
+            }
+            Expr::FunctionCall(func_name, args) => {
+                match blocks::lookup(func_name) {
+                    Some(block) if block.return_type() == Some(Type::Bool) => {
+                        self.compile_reporter(block, args, parent_id, current_id)?;
+                    }
+                    _ => bail!("expected bool in condition, recieved: {:?}", condition),
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+
         // never nest!
         let (left, op, right) = if let Expr::Binary(left, op, right) = condition {
             (left, op, right)
         } else {
-            panic!(
+            bail!(
                 "expected binary expression in condition, recieved: {:?}",
                 condition
             );
         };
 
         match op {
-            Operator::Bang => {
-                todo!("this will be removed, i just can't be fucked to bother with the lexer")
-            }
             Operator::EqualEqual => {
-                self.compile_binary_expr(condition, parent_id, current_id);
+                self.compile_binary_expr(condition, parent_id, current_id)
             },
             Operator::And => {
-                self.compile_binary_expr(condition, parent_id, current_id);
+                self.compile_binary_expr(condition, parent_id, current_id)
             }
             Operator::Or => {
-                self.compile_binary_expr(condition, parent_id, current_id);
+                self.compile_binary_expr(condition, parent_id, current_id)
             }
-            Operator::BangEqual => {
-                self.compile_binary_expr(condition, parent_id, current_id);
+            Operator::BangEqual
+            | Operator::Greater
+            | Operator::Less
+            | Operator::GreaterEqual
+            | Operator::LessEqual => {
+                self.compile_binary_expr(condition, parent_id, current_id)
             },
-            Operator::Greater => todo!(),
-            Operator::Less => todo!(),
-            Operator::GreaterEqual => todo!(),
-            Operator::LessEqual => todo!(),
-            _ => panic!("a comparison operator is required as the root operator in a condition, found: {:#?}", op)
+            Operator::Bang => bail!("operator {:?} is not supported yet", op),
+            _ => bail!("a comparison operator is required as the root operator in a condition, found: {:#?}", op)
         }
     }
 
-    fn compile_binary_expr(&mut self, expression: &Expr, parent_id: String, current_id: String) {
-        if parent_id == "a" {
-            panic!()
+    /// compiles `var == "true"` for a variable holding a bool
+    fn compile_bool_from_var(
+        &mut self,
+        var_name: String,
+        expr: &Expr,
+        parent_id: String,
+        current_id: String,
+    ) -> Result<()> {
+        let var_type = self.get_var(self.scope_path.clone(), var_name.clone())?.1;
+        if var_type != Type::Bool {
+            bail!(
+                "expected bool in condition, recieved: {:?} of type: {:?}",
+                expr,
+                var_type
+            );
         }
 
+        let comparison = Expr::Binary(
+            Box::new(Expr::Identifier(var_name)),
+            Operator::EqualEqual,
+            Box::new(Expr::String("true".to_string())),
+        );
+
+        self.compile_binary_expr(&comparison, parent_id, current_id)
+    }
+
+    fn compile_binary_expr(
+        &mut self,
+        expression: &Expr,
+        parent_id: String,
+        current_id: String,
+    ) -> Result<()> {
         match expression {
             Expr::Binary(left, op, right) => match op {
                 // string concat lol
@@ -164,18 +684,17 @@ impl Compiler {
                         "operator_join",
                         current_id,
                         parent_id,
-                    );
+                    )?;
                 }
-                Operator::Bang => todo!(),
                 Operator::EqualEqual => {
                     let inputs = HashMap::from([
                         (
                             "OPERAND1".to_string(),
-                            self.value_from_expr(left, current_id.clone(), None),
+                            self.value_from_expr(left, current_id.clone(), None)?,
                         ),
                         (
                             "OPERAND2".to_string(),
-                            self.value_from_expr(right, current_id.clone(), None),
+                            self.value_from_expr(right, current_id.clone(), None)?,
                         ),
                     ]);
 
@@ -189,8 +708,36 @@ impl Compiler {
                         current_id,
                     );
                 }
-                Operator::BangEqual => {
-                    let equals_id = self.gen_block_id();
+                Operator::Less => {
+                    self.compile_simple_operator(
+                        "OPERAND1",
+                        "OPERAND2",
+                        left,
+                        right,
+                        "operator_lt",
+                        current_id,
+                        parent_id,
+                    )?;
+                }
+                Operator::Greater => {
+                    self.compile_simple_operator(
+                        "OPERAND1",
+                        "OPERAND2",
+                        left,
+                        right,
+                        "operator_gt",
+                        current_id,
+                        parent_id,
+                    )?;
+                }
+                // scratch only has `=`, `<` and `>`, the rest are negations of those
+                Operator::BangEqual | Operator::LessEqual | Operator::GreaterEqual => {
+                    let negated = match op {
+                        Operator::BangEqual => Operator::EqualEqual,
+                        Operator::LessEqual => Operator::Greater,
+                        _ => Operator::Less,
+                    };
+                    let operand_id = self.gen_block_id();
 
                     self.push_block(
                         &Block {
@@ -198,7 +745,7 @@ impl Compiler {
                             parent: Some(parent_id.clone()),
                             inputs: Some(HashMap::from([(
                                 "OPERAND".to_string(),
-                                json!([2, equals_id]),
+                                json!([2, operand_id]),
                             )])),
                             shadow: Some(false),
                             top_level: Some(false),
@@ -208,14 +755,9 @@ impl Compiler {
                     );
 
                     // FIXME: expensive cloning(?)
-                    let expression =
-                        Expr::Binary(left.clone(), Operator::EqualEqual, right.clone());
-                    self.compile_binary_expr(&expression, current_id, equals_id);
-                }
-                Operator::Greater => todo!(),
-                Operator::Less => todo!(),
-                Operator::GreaterEqual => todo!(),
-                Operator::LessEqual => todo!(),
+                    let expression = Expr::Binary(left.clone(), negated, right.clone());
+                    self.compile_binary_expr(&expression, current_id, operand_id)?;
+                }
                 Operator::Minus => {
                     self.compile_simple_operator(
                         "NUM1",
@@ -225,7 +767,7 @@ impl Compiler {
                         "operator_subtract",
                         current_id,
                         parent_id,
-                    );
+                    )?;
                 }
                 Operator::Plus => {
                     self.compile_simple_operator(
@@ -236,7 +778,7 @@ impl Compiler {
                         "operator_add",
                         current_id,
                         parent_id,
-                    );
+                    )?;
                 }
                 Operator::Slash => {
                     self.compile_simple_operator(
@@ -247,7 +789,7 @@ impl Compiler {
                         "operator_divide",
                         current_id,
                         parent_id,
-                    );
+                    )?;
                 }
                 Operator::Star => {
                     self.compile_simple_operator(
@@ -258,19 +800,18 @@ impl Compiler {
                         "operator_multiply",
                         current_id,
                         parent_id,
-                    );
+                    )?;
                 }
-                Operator::Caret => todo!(),
                 Operator::None => panic!("we should never be here."),
                 Operator::And => {
                     let inputs = HashMap::from([
                         (
                             "OPERAND1".to_string(),
-                            self.value_from_expr(left, current_id.clone(), None),
+                            self.value_from_expr(left, current_id.clone(), None)?,
                         ),
                         (
                             "OPERAND2".to_string(),
-                            self.value_from_expr(right, current_id.clone(), None),
+                            self.value_from_expr(right, current_id.clone(), None)?,
                         ),
                     ]);
 
@@ -288,11 +829,11 @@ impl Compiler {
                     let inputs = HashMap::from([
                         (
                             "OPERAND1".to_string(),
-                            self.value_from_expr(left, current_id.clone(), None),
+                            self.value_from_expr(left, current_id.clone(), None)?,
                         ),
                         (
                             "OPERAND2".to_string(),
-                            self.value_from_expr(right, current_id.clone(), None),
+                            self.value_from_expr(right, current_id.clone(), None)?,
                         ),
                     ]);
 
@@ -306,13 +847,20 @@ impl Compiler {
                         current_id,
                     );
                 }
-                Operator::PlusEqual => todo!(),
-                Operator::MinusEqual => todo!(),
-                Operator::StarEqual => todo!(),
-                Operator::SlashEqual => todo!(),
+                Operator::Bang | Operator::Caret => {
+                    bail!("operator {:?} is not supported yet", op)
+                }
+                Operator::PlusEqual
+                | Operator::MinusEqual
+                | Operator::StarEqual
+                | Operator::SlashEqual => {
+                    bail!("{:?} can only be used to mutate a variable", op)
+                }
             },
-            _ => todo!(),
+            _ => bail!("expected binary expression, recieved: {:?}", expression),
         }
+
+        Ok(())
     }
 
     fn compile_simple_operator(
@@ -324,9 +872,9 @@ impl Compiler {
         opcode: &str,
         current_id: String,
         parent_id: String,
-    ) {
-        let val1 = self.value_from_expr(val1, current_id.clone(), None);
-        let val2 = self.value_from_expr(val2, current_id.clone(), None);
+    ) -> Result<()> {
+        let val1 = self.value_from_expr(val1, current_id.clone(), None)?;
+        let val2 = self.value_from_expr(val2, current_id.clone(), None)?;
 
         self.push_block(
             &Block {
@@ -341,7 +889,9 @@ impl Compiler {
                 ..Default::default()
             },
             current_id,
-        )
+        );
+
+        Ok(())
     }
 
     fn compile_conditional_control(
@@ -353,10 +903,10 @@ impl Compiler {
         parent_id: String,
         index: usize,
         body_len: usize,
-    ) {
+    ) -> Result<()> {
         let condition_id = self.gen_block_id();
 
-        self.compile_condition(cond, current_id.clone(), condition_id.clone());
+        self.compile_condition(cond, current_id.clone(), condition_id.clone())?;
 
         let mut inputs = HashMap::new();
         inputs.insert("CONDITION".to_string(), json!([2, condition_id]));
@@ -364,13 +914,13 @@ impl Compiler {
         // TODO: remove this code-duplication
         if let Some(substack) = substacks.0 {
             let substack_id = self.peek_next_block_id();
-            self.compile_body_statements(substack, current_id.clone(), None);
+            self.compile_body_statements(substack, current_id.clone(), None)?;
             inputs.insert("SUBSTACK".to_string(), json!([2, substack_id]));
         }
 
         if let Some(substack) = substacks.1 {
             let substack_id = self.peek_next_block_id();
-            self.compile_body_statements(substack, current_id.clone(), None);
+            self.compile_body_statements(substack, current_id.clone(), None)?;
             inputs.insert("SUBSTACK2".to_string(), json!([2, substack_id]));
         }
 
@@ -392,6 +942,8 @@ impl Compiler {
             },
             current_id,
         );
+
+        Ok(())
     }
 
     fn value_from_expr(
@@ -399,27 +951,58 @@ impl Compiler {
         expr: &Expr,
         parent_id: String,
         current_id: Option<String>,
-    ) -> Value {
-        match expr {
+    ) -> Result<Value> {
+        let value = match expr {
             Expr::String(value) => json!([1, [10, value.to_string()]]),
             Expr::Number(value) => json!([1, [10, value.to_string()]]),
             Expr::Bool(value) => json!([1, [10, value.to_string()]]),
             Expr::Identifier(ident) => {
-                if self.var_exists(self.scope_path.clone(), ident.clone()) {
+                let stack_slot = if self.var_exists(self.scope_path.clone(), ident.clone()) {
+                    let var_id = self.get_var_id(self.scope_path.clone(), ident.clone())?;
+                    self.stack_slots.get(&var_id).copied()
+                } else {
+                    None
+                };
+
+                if let Some(slot) = stack_slot {
+                    let item_id = self.gen_block_id();
+                    let index = self.stack_index(slot, item_id.clone());
+                    let list_id = self.stack_list();
+
+                    self.push_block(
+                        &Block {
+                            opcode: "data_itemoflist".to_string(),
+                            parent: Some(current_id.unwrap_or(parent_id)),
+                            inputs: Some(HashMap::from([("INDEX".to_string(), index)])),
+                            fields: Some(json!({"LIST": ["!stack", list_id]})),
+                            shadow: Some(false),
+                            top_level: Some(false),
+                            ..Default::default()
+                        },
+                        item_id.clone(),
+                    );
+
+                    json!([3, item_id, [10, ""]])
+                } else if self.var_exists(self.scope_path.clone(), ident.clone()) {
                     json!([
                         3,
                         [
                             12,
                             ident.clone(),
-                            self.get_var_id(self.scope_path.clone(), ident.clone())
+                            self.get_var_id(self.scope_path.clone(), ident.clone())?
                         ],
                         [10, ""]
                     ])
                 } else {
+                    let opcode = match self.arg_type(ident) {
+                        Some(Type::Bool) => "argument_reporter_boolean",
+                        _ => "argument_reporter_string_number",
+                    };
+
                     let child_id = self.gen_block_id();
                     self.push_block(
                         &Block {
-                            opcode: "argument_reporter_string_number".to_string(),
+                            opcode: opcode.to_string(),
                             parent: Some(parent_id.to_string()),
                             fields: Some(json!({"VALUE": [ident, serde_json::Value::Null]})),
                             shadow: Some(false),
@@ -435,7 +1018,7 @@ impl Compiler {
             Expr::Binary(_, op, _) => {
                 // TODO: type checking here, some operators can't be used as input for other operators
                 let id = self.gen_block_id();
-                self.compile_binary_expr(expr, current_id.unwrap(), id.clone());
+                self.compile_binary_expr(expr, current_id.unwrap_or(parent_id), id.clone())?;
                 // TODO: the "3" here shouldn't be static, see comments above
                 // Project::Inputs
                 //
@@ -446,29 +1029,40 @@ impl Compiler {
 
                 json!([3, id.to_string(), [10, ""]])
             }
-            Expr::FunctionCall(func_name, args) => {
-                self.compile_function_call(
-                    func_name.clone(),
-                    args.clone(),
-                    current_id.unwrap(),
-                    parent_id,
-                    1,
-                    3,
+            Expr::FunctionCall(func_name, args) if func_name == "block" => {
+                let id = self.gen_block_id();
+                let (opcode, inputs, fields) = self.compile_raw_block(args, id.clone())?;
+
+                self.push_block(
+                    &Block {
+                        opcode,
+                        parent: Some(current_id.unwrap_or(parent_id)),
+                        inputs: Some(inputs),
+                        fields,
+                        shadow: Some(false),
+                        top_level: Some(false),
+                        ..Block::default()
+                    },
+                    id.clone(),
                 );
 
-                let return_var_name = format!("!func_var_{}", func_name);
+                json!([3, id, [10, ""]])
+            }
+            Expr::Map(_) => bail!("maps can only be used as arguments to block"),
+            Expr::FunctionCall(func_name, args) if blocks::lookup(func_name).is_some() => {
+                let id = self.gen_block_id();
+                let block = blocks::lookup(func_name).unwrap();
+                self.compile_reporter(block, args, current_id.unwrap_or(parent_id), id.clone())?;
 
-                json!([
-                    3,
-                    [
-                        12,
-                        return_var_name,
-                        self.get_var_id(self.scope_path.clone(), return_var_name.clone())
-                    ],
-                    [10, ""]
-                ])
+                json!([3, id, [10, ""]])
             }
-        }
+            // calls to declared functions are hoisted out of expressions by `lower_calls`
+            Expr::FunctionCall(func_name, _) => {
+                bail!("function {} is not declared", func_name)
+            }
+        };
+
+        Ok(value)
     }
 
     fn compile_function_call(
@@ -479,185 +1073,194 @@ impl Compiler {
         parent_id: String,
         index: usize,
         body_len: usize,
-    ) {
-        let opcode = match func_name.as_str() {
-            "say" => "looks_say",
-            _ => "procedures_call",
-        };
-
-        match opcode {
-            // FIXME: we only care about the first expression, lel
-            "looks_say" => match &args[0] {
-                Expr::Number(_) => todo!(),
-                Expr::String(string) => {
-                    let mut inputs = HashMap::new();
-                    let value = json!([1, [10, string,]]);
-                    inputs.insert("MESSAGE".to_string(), value);
-
-                    self.next_block_id = if (index + 1) >= body_len {
-                        None
-                    } else {
-                        Some(self.peek_next_block_id())
-                    };
+    ) -> Result<()> {
+        match func_name.as_str() {
+            "stop_all" | "stop_this_script" | "stop_other_scripts" => {
+                self.assert_arg_count(&func_name, &args, 0)?;
+
+                // "all" and "this script" are cap blocks, nothing may follow them
+                let (stop_option, hasnext) = match func_name.as_str() {
+                    "stop_all" => ("all", false),
+                    "stop_this_script" => ("this script", false),
+                    _ if self.current_target.0.is_stage => ("other scripts in stage", true),
+                    _ => ("other scripts in sprite", true),
+                };
 
-                    self.push_block(
-                        &Block {
-                            opcode: "looks_say".to_string(),
-                            parent: Some(parent_id.clone()),
-                            inputs: Some(inputs),
-                            next: self.next_block_id.clone(),
-                            ..Block::default()
-                        },
-                        current_id,
-                    );
+                if !hasnext && (index + 1) < body_len {
+                    bail!("{} must be final statement in body/branch", func_name);
                 }
-                Expr::Identifier(ident) => {
-                    let looks_say_id = current_id.clone();
-
-                    let value = if self.var_exists(self.scope_path.clone(), ident.clone()) {
-                        json!([
-                            3,
-                            [
-                                12,
-                                ident.clone(),
-                                self.get_var_id(self.scope_path.clone(), ident.clone())
-                            ],
-                            [10, ""]
-                        ])
-                    } else {
-                        let arg_reporter_id = self.gen_block_id();
-
-                        self.push_block(
-                            &Block {
-                                opcode: "argument_reporter_string_number".to_string(),
-                                parent: Some(looks_say_id.clone()),
-                                fields: Some(json!({"VALUE": [ident, Value::Null]})),
-                                shadow: Some(false),
-                                top_level: Some(false),
-                                ..Block::default()
-                            },
-                            arg_reporter_id.clone(),
-                        );
-
-                        json!([3, arg_reporter_id, [10, ""]])
-                    };
 
-                    let mut inputs = HashMap::new();
-                    inputs.insert("MESSAGE".to_string(), value);
-
-                    self.next_block_id = if (index + 1) >= body_len {
-                        None
-                    } else {
-                        Some(self.peek_next_block_id())
-                    };
-
-                    self.push_block(
-                        &Block {
-                            opcode: "looks_say".to_string(),
-                            parent: Some(parent_id.clone()),
-                            inputs: Some(inputs),
-                            next: self.next_block_id.clone(),
-                            ..Block::default()
-                        },
-                        looks_say_id.clone(),
-                    );
-                }
-                Expr::Bool(_) => todo!(),
-                Expr::Binary(_, _, _) => {
-                    let new_id = self.gen_block_id();
+                self.compile_stack_block(
+                    Block {
+                        opcode: "control_stop".to_string(),
+                        parent: Some(parent_id),
+                        fields: Some(json!({"STOP_OPTION": [stop_option, Value::Null]})),
+                        shadow: Some(false),
+                        top_level: Some(false),
+                        mutation: Some(Mutation {
+                            tag_name: "mutation".to_string(),
+                            children: vec![],
+                            hasnext: Some(hasnext.to_string()),
+                            ..Default::default()
+                        }),
+                        ..Block::default()
+                    },
+                    current_id,
+                    index,
+                    body_len,
+                );
+            }
+            // lowered from calls to other targets
+            "!broadcast_and_wait" => {
+                let qualified_name = Compiler::literal_option(&args[0]).unwrap();
+                let (_, broadcast_id, _, _, _) = self
+                    .remote_functions
+                    .iter()
+                    .find(|(name, _, _, _, _)| *name == qualified_name)
+                    .unwrap();
+
+                self.compile_stack_block(
+                    Block {
+                        opcode: "event_broadcastandwait".to_string(),
+                        parent: Some(parent_id),
+                        inputs: Some(HashMap::from([(
+                            "BROADCAST_INPUT".to_string(),
+                            json!([1, [11, qualified_name, broadcast_id]]),
+                        )])),
+                        shadow: Some(false),
+                        top_level: Some(false),
+                        ..Block::default()
+                    },
+                    current_id,
+                    index,
+                    body_len,
+                );
+            }
+            // lowered from `#[recursive]` functions
+            "!stack_push" | "!stack_pop" => {
+                let item_id = self.gen_block_id();
+                let list_id = self.stack_list();
 
-                    self.compile_binary_expr(&args[0], current_id.clone(), new_id.clone());
+                let (opcode, input) = if func_name == "!stack_push" {
+                    ("data_addtolist", ("ITEM", json!([1, [10, ""]])))
+                } else {
+                    ("data_deleteoflist", ("INDEX", json!([1, [7, "last"]])))
+                };
 
-                    let mut inputs = HashMap::new();
-                    inputs.insert("MESSAGE".to_string(), json!([3, new_id, [10, ""]]));
+                self.push_block(
+                    &Block {
+                        opcode: opcode.to_string(),
+                        parent: Some(current_id.clone()),
+                        inputs: Some(HashMap::from([(input.0.to_string(), input.1)])),
+                        fields: Some(json!({"LIST": ["!stack", list_id]})),
+                        shadow: Some(false),
+                        top_level: Some(false),
+                        ..Default::default()
+                    },
+                    item_id.clone(),
+                );
 
-                    self.next_block_id = if (index + 1) >= body_len {
-                        None
-                    } else {
-                        Some(self.peek_next_block_id())
-                    };
+                let times = self.value_from_expr(&args[0], current_id.clone(), None)?;
+
+                self.compile_stack_block(
+                    Block {
+                        opcode: "control_repeat".to_string(),
+                        parent: Some(parent_id),
+                        inputs: Some(HashMap::from([
+                            ("TIMES".to_string(), times),
+                            ("SUBSTACK".to_string(), json!([2, item_id])),
+                        ])),
+                        shadow: Some(false),
+                        top_level: Some(false),
+                        ..Block::default()
+                    },
+                    current_id,
+                    index,
+                    body_len,
+                );
+            }
+            "block" => {
+                let (opcode, inputs, fields) = self.compile_raw_block(&args, current_id.clone())?;
 
-                    self.push_block(
-                        &Block {
-                            opcode: "looks_say".to_string(),
-                            parent: Some(parent_id.to_string()),
-                            inputs: Some(inputs),
-                            next: self.next_block_id.clone(),
-                            ..Block::default()
-                        },
-                        current_id,
-                    );
+                self.compile_stack_block(
+                    Block {
+                        opcode,
+                        parent: Some(parent_id),
+                        inputs: Some(inputs),
+                        fields,
+                        shadow: Some(false),
+                        top_level: Some(false),
+                        ..Block::default()
+                    },
+                    current_id,
+                    index,
+                    body_len,
+                );
+            }
+            _ if blocks::lookup(&func_name).is_some() => {
+                let block = blocks::lookup(&func_name).unwrap();
+
+                match block.shape {
+                    Shape::Stack => {}
+                    Shape::Cap if (index + 1) >= body_len => {}
+                    Shape::Cap => bail!("{} must be final statement in body/branch", func_name),
+                    Shape::Hat => bail!("{} is an event, not a function", func_name),
+                    Shape::Reporter(_) => bail!("{} cannot be used as a statement", func_name),
                 }
-                _ => panic!(),
-            },
+
+                let (inputs, fields) =
+                    self.compile_block_inputs(block, &args, current_id.clone())?;
+
+                self.compile_stack_block(
+                    Block {
+                        opcode: block.opcode.to_string(),
+                        parent: Some(parent_id),
+                        inputs: Some(inputs),
+                        fields,
+                        shadow: Some(false),
+                        top_level: Some(false),
+                        ..Block::default()
+                    },
+                    current_id,
+                    index,
+                    body_len,
+                );
+            }
             _ => {
                 let mut inputs: HashMap<String, Value> = HashMap::new();
-                let mut proc_codes = func_name.clone();
                 let mut argument_ids = String::from("[");
 
-                let mut expr_block_id = None;
-
-                for (index, arg) in args.into_iter().enumerate() {
-                    let proc_code = match arg {
-                        Expr::Number(_) => " %s",
-                        Expr::String(_) => " %s",
-                        Expr::Identifier(_) => " %s",
-                        // FIXME:: accept more than just string type
-                        Expr::Bool(_) => todo!(),
-                        Expr::Binary(_, _, _) => {
-                            expr_block_id = Some(self.gen_block_id());
-                            " %s"
-                        }
-                        _ => todo!(),
-                    };
+                let Some(function_table) = self.arg_table.get(&func_name).cloned() else {
+                    bail!(
+                        "function {} is not declared in {}",
+                        func_name,
+                        self.current_target.0.name
+                    );
+                };
+                self.assert_arg_count(&func_name, &args, function_table.len())?;
 
-                    proc_codes.push_str(proc_code);
+                let (proc_code, arg_order, warp) = self.prototype_table[&func_name].clone();
 
+                for (index, position) in arg_order.iter().enumerate() {
                     if index != 0 {
                         argument_ids.push_str(", ");
                     }
 
-                    // TODO: just add error checking instead of crashing please
-                    let arg_id = &self.arg_table.get(&func_name).unwrap()[index].0;
-                    argument_ids.push_str(&format!("\"{}\"", arg_id.to_string()));
+                    argument_ids.push_str(&format!("\"{}\"", function_table[*position].0));
+                }
 
-                    match arg {
-                        Expr::Number(value) => {
-                            inputs.insert(arg_id.to_string(), json!([1, [10, value]]));
-                        }
-                        Expr::String(value) => {
-                            inputs.insert(arg_id.to_string(), json!([1, [10, value]]));
-                        }
-                        Expr::Identifier(ident) => {
-                            inputs.insert(
-                                arg_id.to_string(),
-                                json!([
-                                    3,
-                                    [
-                                        12,
-                                        ident,
-                                        self.get_var_id(self.scope_path.clone(), ident.clone())
-                                    ]
-                                ]),
-                            );
-                        }
-                        Expr::Bool(_) => todo!(),
-                        Expr::Binary(_, _, _) => {
-                            let expr_block_id = expr_block_id.clone();
+                for (index, arg) in args.into_iter().enumerate() {
+                    let (arg_id, _, arg_type) = &function_table[index];
 
-                            inputs.insert(
-                                arg_id.clone().to_string(),
-                                json!([3, expr_block_id.clone().unwrap(), [10, ""]]),
-                            );
+                    let value = if *arg_type == Type::Bool {
+                        let condition_id = self.gen_block_id();
+                        self.compile_condition(&arg, current_id.clone(), condition_id.clone())?;
+                        json!([2, condition_id])
+                    } else {
+                        self.value_from_expr(&arg, current_id.clone(), None)?
+                    };
 
-                            self.compile_binary_expr(
-                                &arg,
-                                current_id.clone(),
-                                expr_block_id.unwrap(),
-                            );
-                        }
-                        _ => todo!(),
-                    }
+                    inputs.insert(arg_id.to_string(), value);
                 }
 
                 argument_ids.push_str("]");
@@ -676,11 +1279,11 @@ impl Compiler {
                         mutation: Some(Mutation {
                             tag_name: "mutation".to_string(),
                             children: vec![],
-                            proccode: Some(proc_codes),
+                            proccode: Some(proc_code),
                             argumentids: Some(argument_ids),
                             argumentnames: None,
                             argumentdefaults: None,
-                            warp: Some("false".to_string()),
+                            warp: Some(warp.to_string()),
                             ..Default::default()
                         }),
                         next: self.next_block_id.clone(),
@@ -690,15 +1293,865 @@ impl Compiler {
                 );
             }
         }
+
+        Ok(())
+    }
+
+    /// links a statement block to the statement after it (if any)
+    /// and pushes it, must be called after all of its inputs are compiled
+    fn compile_stack_block(
+        &mut self,
+        block: Block,
+        current_id: String,
+        index: usize,
+        body_len: usize,
+    ) {
+        self.next_block_id = if (index + 1) >= body_len {
+            None
+        } else {
+            Some(self.peek_next_block_id())
+        };
+
+        self.push_block(
+            &Block {
+                next: self.next_block_id.clone(),
+                ..block
+            },
+            current_id,
+        );
+    }
+
+    fn require_extension(&self, extension: Extension, func_name: &str) -> Result<()> {
+        if !self.extensions.contains(&extension) {
+            bail!(
+                "{} requires the {:?} extension, add it to `extensions` in project.toml",
+                func_name,
+                extension
+            );
+        }
+
+        Ok(())
+    }
+
+    /// compiles the arguments of a built-in into its inputs and fields
+    fn compile_block_inputs(
+        &mut self,
+        block: &BlockDef,
+        args: &[Expr],
+        block_id: String,
+    ) -> Result<(HashMap<String, Value>, Option<Value>)> {
+        if let Some(extension) = &block.extension {
+            self.require_extension(extension.clone(), block.name)?;
+        }
+
+        self.assert_arg_count(block.name, args, block.inputs.len())?;
+
+        let mut inputs = HashMap::new();
+        let mut fields = serde_json::Map::new();
+
+        for (name, value) in block.fields {
+            fields.insert(name.to_string(), json!([value, Value::Null]));
+        }
+
+        for ((name, input), arg) in block.inputs.iter().zip(args) {
+            self.check_input_type(block.name, input, arg)?;
+
+            if let Input::Field(options) = input {
+                let option = Compiler::literal_option(arg)
+                    .and_then(|option| blocks::resolve_option(options, &option));
+
+                let Some(option) = option else {
+                    bail!("expected one of: {:?}, recieved: {:?}", options, arg);
+                };
+
+                fields.insert(name.to_string(), json!([option, Value::Null]));
+                continue;
+            }
+
+            let value = self.compile_input(input, arg, block_id.clone())?;
+            inputs.insert(name.to_string(), value);
+        }
+
+        let fields = if fields.is_empty() {
+            None
+        } else {
+            Some(Value::Object(fields))
+        };
+
+        Ok((inputs, fields))
+    }
+
+    /// lowers a single argument into the value of an input
+    fn compile_input(&mut self, input: &Input, arg: &Expr, block_id: String) -> Result<Value> {
+        match input {
+            Input::Number | Input::Text => self.value_from_expr(arg, block_id, None),
+            Input::Bool => {
+                let condition_id = self.gen_block_id();
+                self.compile_condition(arg, block_id, condition_id.clone())?;
+                Ok(json!([2, condition_id]))
+            }
+            Input::Color => self.color_from_expr(arg, block_id),
+            Input::Note => {
+                let note = match arg {
+                    Expr::Number(value) if (0.0..=130.0).contains(value) => Some(value.to_string()),
+                    _ => None,
+                };
+
+                self.compile_menu_input(("note", "NOTE", "60"), note, arg, block_id)
+            }
+            Input::Menu(menu) => self.compile_menu(menu, arg, block_id),
+            Input::Field(_) => panic!("fields are not inputs"),
+        }
+    }
+
+    /// `block("opcode", {INPUT: expr, ...}, {FIELD: "value", ...})`, for
+    /// emitting blocks scuff doesn't have a built-in for
+    ///
+    /// returns the opcode, inputs and fields
+    fn compile_raw_block(
+        &mut self,
+        args: &[Expr],
+        block_id: String,
+    ) -> Result<(String, HashMap<String, Value>, Option<Value>)> {
+        if args.is_empty() || args.len() > 3 {
+            bail!("block expects 1 to 3 argument(s), recieved: {}", args.len());
+        }
+
+        let opcode = match &args[0] {
+            Expr::String(opcode) => opcode.clone(),
+            _ => bail!(
+                "the opcode passed to block must be a string, recieved: {:?}",
+                args[0]
+            ),
+        };
+
+        let mut inputs = HashMap::new();
+
+        if let Some(arg) = args.get(1) {
+            let entries = match arg {
+                Expr::Map(entries) => entries,
+                _ => bail!("expected map of inputs, recieved: {:?}", arg),
+            };
+
+            for (name, value) in entries {
+                let input = match self.expr_type(value) {
+                    Some(Type::Bool) => Input::Bool,
+                    _ => Input::Text,
+                };
+
+                let value = self.compile_input(&input, value, block_id.clone())?;
+                inputs.insert(name.clone(), value);
+            }
+        }
+
+        let mut fields = None;
+
+        if let Some(arg) = args.get(2) {
+            let entries = match arg {
+                Expr::Map(entries) => entries,
+                _ => bail!("expected map of fields, recieved: {:?}", arg),
+            };
+
+            let mut map = serde_json::Map::new();
+
+            for (name, value) in entries {
+                match value {
+                    Expr::String(value) => {
+                        map.insert(name.clone(), json!([value, Value::Null]));
+                    }
+                    _ => bail!("field {} must be a string, recieved: {:?}", name, value),
+                }
+            }
+
+            fields = Some(Value::Object(map));
+        }
+
+        Ok((opcode, inputs, fields))
+    }
+
+    fn compile_reporter(
+        &mut self,
+        block: &BlockDef,
+        args: &[Expr],
+        parent_id: String,
+        current_id: String,
+    ) -> Result<()> {
+        if block.return_type().is_none() {
+            bail!("{} does not return a value", block.name);
+        }
+
+        let (inputs, fields) = self.compile_block_inputs(block, args, current_id.clone())?;
+
+        self.push_block(
+            &Block {
+                opcode: block.opcode.to_string(),
+                parent: Some(parent_id),
+                inputs: Some(inputs),
+                fields,
+                shadow: Some(false),
+                top_level: Some(false),
+                ..Block::default()
+            },
+            current_id,
+        );
+
+        Ok(())
+    }
+
+    /// the text of an argument, if it could name a menu option
+    fn literal_option(expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Identifier(name) | Expr::String(name) => Some(name.clone()),
+            Expr::Number(value) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+
+    fn compile_menu(&mut self, menu: &Menu, expr: &Expr, parent_id: String) -> Result<Value> {
+        let option = Compiler::literal_option(expr).and_then(|option| {
+            menu.resolve(&option).or(match expr {
+                Expr::String(_) if menu.open => Some(option),
+                _ => None,
+            })
+        });
+
+        if option.is_none() && !menu.reporters {
+            bail!("expected one of: {:?}, recieved: {:?}", menu.options, expr);
+        }
+
+        let default = menu.options.first().map(|(_, value)| *value).unwrap_or("");
+
+        self.compile_menu_input((menu.opcode, menu.field, default), option, expr, parent_id)
+    }
+
+    /// menus are compiled into their own shadow block, when `option` is `None`
+    /// the expression obscures a shadow holding the default option
+    ///
+    /// `menu` is (opcode, field, default option)
+    fn compile_menu_input(
+        &mut self,
+        menu: (&str, &str, &str),
+        option: Option<String>,
+        expr: &Expr,
+        parent_id: String,
+    ) -> Result<Value> {
+        let (opcode, field, default) = menu;
+
+        if let Some(option) = option {
+            return Ok(json!([
+                1,
+                self.push_shadow(opcode, field, &option, parent_id)
+            ]));
+        }
+
+        let value = self.value_from_expr(expr, parent_id.clone(), None)?;
+
+        if value[0] != 3 {
+            bail!("{:?} is not a valid option for {}", expr, field);
+        }
+
+        let menu_id = self.push_shadow(opcode, field, default, parent_id);
+        Ok(json!([3, value[1], menu_id]))
+    }
+
+    /// pushes a shadow block holding a single field, returning its id
+    fn push_shadow(&mut self, opcode: &str, field: &str, value: &str, parent_id: String) -> String {
+        let shadow_id = self.gen_block_id();
+
+        self.push_block(
+            &Block {
+                opcode: opcode.to_string(),
+                parent: Some(parent_id),
+                fields: Some(json!({ field: [value, Value::Null] })),
+                shadow: Some(true),
+                top_level: Some(false),
+                ..Block::default()
+            },
+            shadow_id.clone(),
+        );
+
+        shadow_id
+    }
+
+    /// colors are `"#rrggbb"` strings, which scratch wants as a color shadow
+    fn color_from_expr(&mut self, expr: &Expr, parent_id: String) -> Result<Value> {
+        let value = match expr {
+            Expr::String(color) => {
+                let is_hex = color.len() == 7
+                    && color.starts_with('#')
+                    && color[1..].chars().all(|char| char.is_ascii_hexdigit());
+
+                if !is_hex {
+                    bail!(
+                        "expected color in the form \"#rrggbb\", recieved: {:?}",
+                        color
+                    );
+                }
+
+                json!([1, [9, color]])
+            }
+            _ => {
+                let mut value = self.value_from_expr(expr, parent_id, None)?;
+
+                // swap out the obscured text shadow for a color picker
+                if value[0] == 3 {
+                    value[2] = json!([9, "#000000"]);
+                }
+
+                value
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn check_input_type(&self, func_name: &str, input: &Input, arg: &Expr) -> Result<()> {
+        let expected = match input {
+            Input::Number => Type::Number,
+            Input::Bool => Type::Bool,
+            Input::Color => Type::String,
+            Input::Text | Input::Note | Input::Menu(_) | Input::Field(_) => return Ok(()),
+        };
+
+        if let Some(actual) = self.expr_type(arg) {
+            if actual != expected {
+                bail!(
+                    "argument {:?} to {} should be of type: {:?}, recieved type: {:?}",
+                    arg,
+                    func_name,
+                    expected,
+                    actual
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// the type of an expression, if it can be known at compile time
+    fn expr_type(&self, expr: &Expr) -> Option<Type> {
+        match expr {
+            Expr::Number(_) => Some(Type::Number),
+            Expr::String(_) => Some(Type::String),
+            Expr::Bool(_) => Some(Type::Bool),
+            Expr::Identifier(ident) => match self.get_var(self.scope_path.clone(), ident.clone()) {
+                Ok((_, var_type)) => Some(var_type),
+                Err(_) => self.arg_type(ident),
+            },
+            Expr::Binary(_, op, _) => match op {
+                Operator::Ampersand => Some(Type::String),
+                Operator::Plus
+                | Operator::Minus
+                | Operator::Star
+                | Operator::Slash
+                | Operator::Caret => Some(Type::Number),
+                _ => Some(Type::Bool),
+            },
+            Expr::FunctionCall(func_name, _) => match blocks::lookup(func_name) {
+                Some(block) => block.return_type(),
+                None => self.return_table.get(func_name).cloned(),
+            },
+            Expr::Map(_) => None,
+        }
+    }
+
+    fn assert_arg_count(&self, func_name: &str, args: &[Expr], expected: usize) -> Result<()> {
+        if args.len() != expected {
+            bail!(
+                "{} expects {} argument(s), recieved: {}",
+                func_name,
+                expected,
+                args.len()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// hoists calls to declared functions out of expressions, each call is compiled
+    /// as a `procedures_call` before the statement using it and is replaced with a
+    /// read of the function's return variable
+    fn lower_calls(&mut self, body: &[Stmt]) -> Result<Vec<Stmt>> {
+        let mut lowered = Vec::new();
+
+        for stmt in body {
+            let stmt = &self.lower_reevaluated_inputs(stmt)?;
+
+            // Vec<(Placeholder, FunctionName, Arguments)>
+            let mut calls: Vec<(String, String, Vec<Expr>)> = Vec::new();
+            let mut error = None;
+            let stmt = Compiler::map_stmt_exprs(stmt, &mut |expr| {
+                self.hoist_calls(expr, &mut calls).unwrap_or_else(|err| {
+                    error.get_or_insert(err);
+                    expr.clone()
+                })
+            });
+
+            if let Some(error) = error {
+                return Err(error);
+            }
+
+            let mut names = HashMap::new();
+            let mut hoisted = Vec::new();
+
+            for (index, (placeholder, func_name, args)) in calls.iter().enumerate() {
+                let args = args
+                    .iter()
+                    .map(|arg| Compiler::rename_reads(arg, &names))
+                    .collect();
+                hoisted.push(Stmt::FunctionCall(func_name.clone(), args));
+
+                // the return variable is overwritten by the next call, so a result that
+                // isn't used straight away is copied into a temporary variable first
+                let consumer = calls[index + 1..].iter().position(|(_, _, args)| {
+                    args.iter()
+                        .any(|arg| Compiler::expr_reads(arg, placeholder))
+                });
+                let used_next = match consumer {
+                    Some(position) => position == 0,
+                    None => index + 1 == calls.len(),
+                };
+
+                let return_var_name = format!("!func_var_{}", func_name);
+
+                if used_next {
+                    names.insert(placeholder.clone(), return_var_name);
+                } else {
+                    self.temp_id += 1;
+                    let temp_name = format!("!call_tmp_{}", self.temp_id);
+
+                    hoisted.push(Stmt::VariableDeclaration(
+                        temp_name.clone(),
+                        self.return_table[func_name].clone(),
+                        Expr::Identifier(return_var_name),
+                    ));
+                    names.insert(placeholder.clone(), temp_name);
+                }
+            }
+
+            let stmt =
+                Compiler::map_stmt_exprs(&stmt, &mut |expr| Compiler::rename_reads(expr, &names));
+
+            let mut expanded = Vec::new();
+            for stmt in hoisted {
+                expanded.extend(self.expand_call(stmt)?);
+            }
+            let hoisted = expanded;
+
+            lowered.extend(hoisted.clone());
+
+            match stmt {
+                // the condition is evaluated again at the end of every iteration
+                Stmt::While(cond, mut body) => {
+                    body.extend(hoisted.into_iter().map(|stmt| match stmt {
+                        Stmt::VariableDeclaration(var_name, _, expr) => {
+                            Stmt::VariableAssignment(var_name, expr)
+                        }
+                        _ => stmt,
+                    }));
+                    lowered.push(Stmt::While(cond, body));
+                }
+                _ => lowered.extend(self.expand_call(stmt)?),
+            }
+        }
+
+        Ok(lowered)
+    }
+
+    /// scratch checks the condition of `wait_until` until it's true, a hoisted call would only
+    /// run once, so it's lowered into `while cond == false {}` which runs the calls again
+    /// before every check, raw blocks might check their bool inputs any number of times
+    /// so calls in them are rejected
+    fn lower_reevaluated_inputs(&self, stmt: &Stmt) -> Result<Stmt> {
+        match stmt {
+            Stmt::FunctionCall(func_name, args)
+                if func_name == "wait_until"
+                    && args.len() == 1
+                    && self.calls_function(&args[0]) =>
+            {
+                let not_yet = Expr::Binary(
+                    Box::new(args[0].clone()),
+                    Operator::EqualEqual,
+                    Box::new(Expr::Bool(false)),
+                );
+
+                Ok(Stmt::While(not_yet, Vec::new()))
+            }
+            Stmt::FunctionCall(func_name, args) if func_name == "block" => {
+                if let Some(Expr::Map(entries)) = args.get(1) {
+                    for (name, value) in entries {
+                        if self.expr_type(value) == Some(Type::Bool) && self.calls_function(value) {
+                            bail!(
+                                "input {} of a raw block cannot call a function, scratch may check it more than once",
+                                name
+                            );
+                        }
+                    }
+                }
+
+                Ok(stmt.clone())
+            }
+            _ => Ok(stmt.clone()),
+        }
+    }
+
+    /// whether an expression calls a function that `hoist_calls` would hoist
+    fn calls_function(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::FunctionCall(func_name, args) => {
+                self.return_table.contains_key(func_name)
+                    || args.iter().any(|arg| self.calls_function(arg))
+            }
+            Expr::Binary(left, _, right) => self.calls_function(left) || self.calls_function(right),
+            Expr::Map(entries) => entries.iter().any(|(_, value)| self.calls_function(value)),
+            _ => false,
+        }
+    }
+
+    /// replaces a call to another target or to an inline function with the statements it runs
+    fn expand_call(&mut self, stmt: Stmt) -> Result<Vec<Stmt>> {
+        match &stmt {
+            Stmt::FunctionCall(func_name, args)
+                if self.inline_functions.contains_key(func_name) =>
+            {
+                let inlined = self.inline_call(func_name, args)?;
+                self.lower_calls(&inlined)
+            }
+            _ => self.expand_remote_call(stmt),
+        }
+    }
+
+    /// the body of an inline function with its arguments bound to temporaries,
+    /// a final `return` sets the return variable just like a `procedures_call` would
+    fn inline_call(&mut self, func_name: &str, args: &[Expr]) -> Result<Vec<Stmt>> {
+        let (params, body) = self.inline_functions[func_name].clone();
+        self.assert_arg_count(func_name, args, params.len())?;
+
+        // every call site shares one set of temporaries, inline functions can't
+        // call back into themselves so they're never in use twice at once
+        let prefix = format!("!inline_{}_", func_name);
+
+        // locals are renamed too, so they can't clash with the caller's variables
+        let mut names = HashMap::new();
+        let mut locals = Vec::new();
+        Compiler::collect_locals(&body, &mut |var_name, var_type| {
+            locals.push((var_name.to_string(), var_type.clone()));
+        });
+        for (var_name, var_type) in locals {
+            let temp_name = format!("{}{}", prefix, var_name);
+            self.declare_inline_var(&temp_name, var_type);
+            names.insert(var_name, temp_name);
+        }
+
+        let mut inlined = Vec::new();
+        for ((arg_name, arg_type), arg) in params.iter().zip(args) {
+            let temp_name = format!("{}{}", prefix, arg_name);
+            self.declare_inline_var(&temp_name, arg_type.clone());
+            inlined.push(Stmt::VariableAssignment(temp_name.clone(), arg.clone()));
+            names.insert(arg_name.clone(), temp_name);
+        }
+
+        let return_var_name = format!("!func_var_{}", func_name);
+        inlined.extend(
+            Compiler::rename_vars(&body, &names)
+                .into_iter()
+                .map(|stmt| match stmt {
+                    Stmt::Return(expr) => Stmt::VariableAssignment(return_var_name.clone(), expr),
+                    _ => stmt,
+                }),
+        );
+
+        Ok(inlined)
+    }
+
+    /// declares a temporary of an inline function the first time it's inlined in a target,
+    /// outside of any stack frame as it's shared by every call site
+    fn declare_inline_var(&mut self, var_name: &str, var_type: Type) {
+        if self.var_exists(Vec::new(), var_name.to_string()) {
+            return;
+        }
+
+        let stack_frame = self.stack_frame.take();
+        self.push_var(Vec::new(), var_name.to_string(), var_type);
+        self.stack_frame = stack_frame;
+    }
+
+    fn collect_locals(body: &[Stmt], f: &mut dyn FnMut(&str, &Type)) {
+        for stmt in body {
+            match stmt {
+                Stmt::VariableDeclaration(var_name, var_type, _) => f(var_name, var_type),
+                Stmt::If(_, body_true, body_false) => {
+                    Compiler::collect_locals(body_true, f);
+                    if let Some(body_false) = body_false {
+                        Compiler::collect_locals(body_false, f);
+                    }
+                }
+                Stmt::While(_, body) => Compiler::collect_locals(body, f),
+                _ => {}
+            }
+        }
+    }
+
+    /// `rename_reads` for every statement in a body, including the variables written to,
+    /// declarations become assignments as the renamed variables are already declared
+    fn rename_vars(body: &[Stmt], names: &HashMap<String, String>) -> Vec<Stmt> {
+        let rename = |var_name: &String| names.get(var_name).unwrap_or(var_name).clone();
+
+        body.iter()
+            .map(|stmt| {
+                match Compiler::map_stmt_exprs(stmt, &mut |expr| {
+                    Compiler::rename_reads(expr, names)
+                }) {
+                    Stmt::VariableDeclaration(var_name, _, expr)
+                    | Stmt::VariableAssignment(var_name, expr) => {
+                        Stmt::VariableAssignment(rename(&var_name), expr)
+                    }
+                    Stmt::VariableMutation(var_name, op, expr) => {
+                        Stmt::VariableMutation(rename(&var_name), op, expr)
+                    }
+                    Stmt::If(cond, body_true, body_false) => Stmt::If(
+                        cond,
+                        Compiler::rename_vars(&body_true, names),
+                        body_false.map(|body| Compiler::rename_vars(&body, names)),
+                    ),
+                    Stmt::While(cond, body) => {
+                        Stmt::While(cond, Compiler::rename_vars(&body, names))
+                    }
+                    stmt => stmt,
+                }
+            })
+            .collect()
+    }
+
+    /// `lower_calls` for a body and every body nested within it
+    fn lower_calls_deep(&mut self, body: &[Stmt]) -> Result<Vec<Stmt>> {
+        self.lower_calls(body)?
+            .into_iter()
+            .map(|stmt| {
+                let stmt = match stmt {
+                    Stmt::If(cond, body_true, body_false) => Stmt::If(
+                        cond,
+                        self.lower_calls_deep(&body_true)?,
+                        body_false
+                            .map(|body_false| self.lower_calls_deep(&body_false))
+                            .transpose()?,
+                    ),
+                    Stmt::While(cond, body) => Stmt::While(cond, self.lower_calls_deep(&body)?),
+                    _ => stmt,
+                };
+
+                Ok(stmt)
+            })
+            .collect()
+    }
+
+    fn count_locals(body: &[Stmt]) -> usize {
+        body.iter()
+            .map(|stmt| match stmt {
+                Stmt::VariableDeclaration(_, _, _) => 1,
+                Stmt::If(_, body_true, body_false) => {
+                    Compiler::count_locals(body_true)
+                        + body_false
+                            .as_ref()
+                            .map_or(0, |body| Compiler::count_locals(body))
+                }
+                Stmt::While(_, body) => Compiler::count_locals(body),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// pushes a stack frame on entry to a recursive function and pops it before every exit,
+    /// the return variable doesn't need a slot as callers read it straight after the call
+    fn lower_stack_frame(
+        body: &[Stmt],
+        frame_size: usize,
+        return_var: &Option<(String, String)>,
+        top_level: bool,
+    ) -> Result<Vec<Stmt>> {
+        if frame_size == 0 {
+            return Ok(body.to_vec());
+        }
+
+        let frame_size_expr = vec![Expr::Number(frame_size as f64)];
+        let pop = Stmt::FunctionCall("!stack_pop".to_string(), frame_size_expr.clone());
+
+        let mut lowered = Vec::new();
+
+        if top_level {
+            lowered.push(Stmt::FunctionCall(
+                "!stack_push".to_string(),
+                frame_size_expr,
+            ));
+        }
+
+        for (index, stmt) in body.iter().enumerate() {
+            match stmt {
+                Stmt::Return(expr) => {
+                    if index + 1 < body.len() {
+                        bail!("return must be final statement in body/branch");
+                    }
+
+                    let Some((return_var_name, _)) = return_var.clone() else {
+                        bail!(
+                            "cannot return in function that isn't declared as having a return type"
+                        );
+                    };
+
+                    lowered.push(Stmt::VariableAssignment(return_var_name, expr.clone()));
+                    lowered.push(pop.clone());
+                    lowered.push(Stmt::FunctionCall(
+                        "stop_this_script".to_string(),
+                        Vec::new(),
+                    ));
+                }
+                Stmt::FunctionCall(func_name, _) if func_name == "stop_this_script" => {
+                    lowered.push(pop.clone());
+                    lowered.push(stmt.clone());
+                }
+                Stmt::If(cond, body_true, body_false) => lowered.push(Stmt::If(
+                    cond.clone(),
+                    Compiler::lower_stack_frame(body_true, frame_size, return_var, false)?,
+                    body_false
+                        .as_ref()
+                        .map(|body| {
+                            Compiler::lower_stack_frame(body, frame_size, return_var, false)
+                        })
+                        .transpose()?,
+                )),
+                Stmt::While(cond, body) => lowered.push(Stmt::While(
+                    cond.clone(),
+                    Compiler::lower_stack_frame(body, frame_size, return_var, false)?,
+                )),
+                _ => lowered.push(stmt.clone()),
+            }
+        }
+
+        let exits = match body.last() {
+            Some(Stmt::Return(_)) => true,
+            Some(Stmt::FunctionCall(func_name, _)) => func_name == "stop_this_script",
+            _ => false,
+        };
+
+        if top_level && !exits {
+            lowered.push(pop);
+        }
+
+        Ok(lowered)
+    }
+
+    fn hoist_calls(
+        &mut self,
+        expr: &Expr,
+        calls: &mut Vec<(String, String, Vec<Expr>)>,
+    ) -> Result<Expr> {
+        let expr = match expr {
+            Expr::Binary(left, op, right) => Expr::Binary(
+                Box::new(self.hoist_calls(left, calls)?),
+                op.clone(),
+                Box::new(self.hoist_calls(right, calls)?),
+            ),
+            Expr::Map(entries) => Expr::Map(
+                entries
+                    .iter()
+                    .map(|(key, value)| Ok((key.clone(), self.hoist_calls(value, calls)?)))
+                    .collect::<Result<_>>()?,
+            ),
+            Expr::FunctionCall(func_name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.hoist_calls(arg, calls))
+                    .collect::<Result<_>>()?;
+
+                match self.return_table.get(func_name) {
+                    Some(Type::Void) => {
+                        bail!("function {} does not return a value", func_name)
+                    }
+                    Some(_) => {
+                        let placeholder = format!("!call_{}", calls.len());
+                        calls.push((placeholder.clone(), func_name.clone(), args));
+                        Expr::Identifier(placeholder)
+                    }
+                    None => Expr::FunctionCall(func_name.clone(), args),
+                }
+            }
+            _ => expr.clone(),
+        };
+
+        Ok(expr)
+    }
+
+    /// applies `f` to every expression directly held by a statement
+    fn map_stmt_exprs(stmt: &Stmt, f: &mut dyn FnMut(&Expr) -> Expr) -> Stmt {
+        match stmt {
+            Stmt::FunctionCall(func_name, args) => {
+                Stmt::FunctionCall(func_name.clone(), args.iter().map(&mut *f).collect())
+            }
+            Stmt::VariableDeclaration(var_name, var_type, expr) => {
+                Stmt::VariableDeclaration(var_name.clone(), var_type.clone(), f(expr))
+            }
+            Stmt::VariableAssignment(var_name, expr) => {
+                Stmt::VariableAssignment(var_name.clone(), f(expr))
+            }
+            Stmt::VariableMutation(var_name, op, expr) => {
+                Stmt::VariableMutation(var_name.clone(), op.clone(), f(expr))
+            }
+            Stmt::If(cond, body_true, body_false) => {
+                Stmt::If(f(cond), body_true.clone(), body_false.clone())
+            }
+            Stmt::While(cond, body) => Stmt::While(f(cond), body.clone()),
+            Stmt::Return(expr) => Stmt::Return(f(expr)),
+            _ => stmt.clone(),
+        }
+    }
+
+    fn rename_reads(expr: &Expr, names: &HashMap<String, String>) -> Expr {
+        match expr {
+            Expr::Identifier(ident) => Expr::Identifier(names.get(ident).unwrap_or(ident).clone()),
+            Expr::Binary(left, op, right) => Expr::Binary(
+                Box::new(Compiler::rename_reads(left, names)),
+                op.clone(),
+                Box::new(Compiler::rename_reads(right, names)),
+            ),
+            Expr::FunctionCall(func_name, args) => Expr::FunctionCall(
+                func_name.clone(),
+                args.iter()
+                    .map(|arg| Compiler::rename_reads(arg, names))
+                    .collect(),
+            ),
+            Expr::Map(entries) => Expr::Map(
+                entries
+                    .iter()
+                    .map(|(key, value)| (key.clone(), Compiler::rename_reads(value, names)))
+                    .collect(),
+            ),
+            _ => expr.clone(),
+        }
+    }
+
+    fn expr_reads(expr: &Expr, name: &str) -> bool {
+        match expr {
+            Expr::Identifier(ident) => ident == name,
+            Expr::Binary(left, _, right) => {
+                Compiler::expr_reads(left, name) || Compiler::expr_reads(right, name)
+            }
+            Expr::FunctionCall(_, args) => args.iter().any(|arg| Compiler::expr_reads(arg, name)),
+            Expr::Map(entries) => entries
+                .iter()
+                .any(|(_, value)| Compiler::expr_reads(value, name)),
+            _ => false,
+        }
     }
 
     fn compile_body_statements(
         &mut self,
-        body: &Vec<Stmt>,
+        body: &[Stmt],
         parent_id: String,
         // Option<(VarName, VarId)>
         return_var: Option<(String, String)>,
-    ) {
+    ) -> Result<()> {
+        let body = &self.lower_calls(body)?;
+
         self.scope_path.push(parent_id.clone());
         for (index, stmt) in body.into_iter().enumerate() {
             let current_id = self.gen_block_id();
@@ -711,15 +2164,8 @@ impl Compiler {
                     parent_id.clone(),
                     index,
                     body.len(),
-                ),
+                )?,
                 Stmt::VariableDeclaration(var_name, var_type, expr) => {
-                    println!(
-                        "FORTNITE (DECLARATION) ->\n current: {} - parent: {} - next: {:?}",
-                        current_id.clone(),
-                        parent_id.clone(),
-                        self.next_block_id,
-                    );
-
                     let var_id =
                         self.push_var(self.scope_path.clone(), var_name.clone(), var_type.clone());
 
@@ -732,7 +2178,7 @@ impl Compiler {
                         parent_id.clone(),
                         index,
                         body.len(),
-                    );
+                    )?;
                 }
                 Stmt::While(cond, body_true) => {
                     self.compile_conditional_control(
@@ -743,7 +2189,7 @@ impl Compiler {
                         parent_id.clone(),
                         index,
                         body.len(),
-                    );
+                    )?;
                 }
                 Stmt::If(cond, body_true, body_false) => {
                     // if-else
@@ -756,7 +2202,7 @@ impl Compiler {
                             parent_id.clone(),
                             index,
                             body.len(),
-                        );
+                        )?;
                     }
                     // if
                     else {
@@ -768,19 +2214,12 @@ impl Compiler {
                             parent_id.clone(),
                             index,
                             body.len(),
-                        );
+                        )?;
                     }
                 }
                 Stmt::VariableAssignment(var_name, expr) => {
                     let (var_id, var_type) =
-                        self.get_var(self.scope_path.clone(), var_name.to_string());
-
-                    println!(
-                        "FORTNITE (ASSIGNMENT) ->\n current: {} - parent: {} - next: {:?}",
-                        current_id.clone(),
-                        parent_id.clone(),
-                        self.next_block_id,
-                    );
+                        self.get_var(self.scope_path.clone(), var_name.to_string())?;
 
                     self.compile_variable_assignment(
                         var_name.clone(),
@@ -791,17 +2230,17 @@ impl Compiler {
                         parent_id.clone(),
                         index,
                         body.len(),
-                    );
+                    )?;
                 }
                 Stmt::VariableMutation(var_name, op, mutation_value) => {
-                    let var_id = self.get_var_id(self.scope_path.clone(), var_name.to_string());
+                    let var_id = self.get_var_id(self.scope_path.clone(), var_name.to_string())?;
 
                     // hack?
                     let op = match op {
-                        crate::parser::MutationOperator::AddEqual => Operator::Plus,
-                        crate::parser::MutationOperator::SubEqual => Operator::Minus,
-                        crate::parser::MutationOperator::MultEqual => Operator::Star,
-                        crate::parser::MutationOperator::DivEqual => Operator::Slash,
+                        crate::parser::MutationOperator::Add => Operator::Plus,
+                        crate::parser::MutationOperator::Sub => Operator::Minus,
+                        crate::parser::MutationOperator::Mult => Operator::Star,
+                        crate::parser::MutationOperator::Div => Operator::Slash,
                     };
 
                     let expr = Expr::Binary(
@@ -810,39 +2249,29 @@ impl Compiler {
                         Box::new(mutation_value.clone()),
                     );
 
-                    let value = self.value_from_expr(&expr, current_id.clone(), None);
-
-                    let mut inputs = HashMap::new();
-                    inputs.insert("VALUE".to_string(), value);
-
-                    self.next_block_id = if (index + 1) >= body.len() {
-                        None
-                    } else {
-                        Some(self.peek_next_block_id())
-                    };
+                    let value = self.value_from_expr(&expr, current_id.clone(), None)?;
 
-                    self.push_block(
-                        &Block {
-                            opcode: "data_setvariableto".to_string(),
-                            parent: Some(parent_id.clone()),
-                            inputs: Some(inputs),
-                            fields: Some(json!({"VARIABLE": [var_name, var_id]})),
-                            next: self.next_block_id.clone(),
-                            ..Block::default()
-                        },
-                        current_id,
+                    let block = self.set_var_block(
+                        var_name.clone(),
+                        var_id,
+                        value,
+                        current_id.clone(),
+                        parent_id.clone(),
                     );
+                    self.compile_stack_block(block, current_id, index, body.len());
                 }
                 Stmt::Return(expr) => {
                     if !((index + 1) >= body.len()) {
-                        panic!("return must be final statement in body/branch");
+                        bail!("return must be final statement in body/branch");
                     }
 
-                    let return_var = return_var.clone().expect(
-                        "cannot return in function that isn't declared as having a return type",
-                    );
+                    let Some(return_var) = return_var.clone() else {
+                        bail!(
+                            "cannot return in function that isn't declared as having a return type"
+                        );
+                    };
 
-                    let value = self.value_from_expr(&expr, current_id.clone(), None);
+                    let value = self.value_from_expr(&expr, current_id.clone(), None)?;
 
                     let mut inputs = HashMap::new();
                     inputs.insert("VALUE".to_string(), value);
@@ -879,10 +2308,12 @@ impl Compiler {
                         stop_block_id,
                     );
                 }
-                _ => panic!("statment: {:#?} not valid in body", stmt),
+                _ => bail!("statment: {:#?} not valid in body", stmt),
             }
         }
         self.scope_path.pop();
+
+        Ok(())
     }
 
     fn compile_variable_assignment(
@@ -895,44 +2326,274 @@ impl Compiler {
         parent_id: String,
         index: usize,
         body_len: usize,
-    ) {
-        let value = self.value_from_expr(expr, parent_id.clone(), Some(current_id.clone()));
-
-        println!(
-            "FORTNITE (AFTER) ->\n current: {} - parent: {} - next: {:?}",
-            current_id.clone(),
-            parent_id.clone(),
-            self.next_block_id,
-        );
-
-        let mut inputs = HashMap::new();
-        inputs.insert("VALUE".to_string(), value);
+    ) -> Result<()> {
+        let value = self.value_from_expr(expr, parent_id.clone(), Some(current_id.clone()))?;
 
         // let current_id = match expr {
         //     Expr::FunctionCall(_, _) => self.gen_block_id(),
         //     _ => current_id.clone(),
         // };
 
-        self.next_block_id = if (index + 1) >= body_len {
-            None
-        } else {
-            Some(self.peek_next_block_id())
-        };
+        let block = self.set_var_block(var_name, var_id, value, current_id.clone(), parent_id);
+        self.compile_stack_block(block, current_id, index, body_len);
 
-        self.push_block(
-            &Block {
+        Ok(())
+    }
+
+    /// sets a variable, or its slot in the stack frame for locals of recursive functions
+    fn set_var_block(
+        &mut self,
+        var_name: String,
+        var_id: String,
+        value: Value,
+        current_id: String,
+        parent_id: String,
+    ) -> Block {
+        match self.stack_slots.get(&var_id).copied() {
+            Some(slot) => {
+                let stack_index = self.stack_index(slot, current_id.clone());
+                let list_id = self.stack_list();
+
+                Block {
+                    opcode: "data_replaceitemoflist".to_string(),
+                    parent: Some(parent_id),
+                    inputs: Some(HashMap::from([
+                        ("INDEX".to_string(), stack_index),
+                        ("ITEM".to_string(), value),
+                    ])),
+                    fields: Some(json!({"LIST": ["!stack", list_id]})),
+                    ..Block::default()
+                }
+            }
+            None => Block {
                 opcode: "data_setvariableto".to_string(),
-                parent: Some(parent_id.clone()),
-                inputs: Some(inputs),
+                parent: Some(parent_id),
+                inputs: Some(HashMap::from([("VALUE".to_string(), value)])),
                 fields: Some(json!({"VARIABLE": [var_name, var_id]})),
-                next: self.next_block_id.clone(),
                 ..Block::default()
             },
-            current_id,
+        }
+    }
+
+    /// the index of a slot in the stack frame, counted back from the top of the stack
+    fn stack_index(&mut self, slot: usize, parent_id: String) -> Value {
+        let (frame_size, _) = self.stack_frame.unwrap();
+        let from_top = frame_size - 1 - slot;
+
+        if from_top == 0 {
+            return json!([1, [7, "last"]]);
+        }
+
+        let subtract_id = self.gen_block_id();
+        let length_id = self.gen_block_id();
+        let list_id = self.stack_list();
+
+        self.push_block(
+            &Block {
+                opcode: "operator_subtract".to_string(),
+                parent: Some(parent_id),
+                inputs: Some(HashMap::from([
+                    ("NUM1".to_string(), json!([3, length_id, [4, ""]])),
+                    ("NUM2".to_string(), json!([1, [4, from_top.to_string()]])),
+                ])),
+                shadow: Some(false),
+                top_level: Some(false),
+                ..Default::default()
+            },
+            subtract_id.clone(),
+        );
+
+        self.push_block(
+            &Block {
+                opcode: "data_lengthoflist".to_string(),
+                parent: Some(subtract_id.clone()),
+                fields: Some(json!({"LIST": ["!stack", list_id]})),
+                shadow: Some(false),
+                top_level: Some(false),
+                ..Default::default()
+            },
+            length_id,
         );
+
+        json!([3, subtract_id, [7, ""]])
+    }
+
+    /// the id of the current target's stack list, created the first time it's needed
+    fn stack_list(&mut self) -> String {
+        if let Some(list_id) = &self.stack_list {
+            return list_id.clone();
+        }
+
+        let list_id = format!("stack_{}", self.target_index);
+        self.project.targets[self.target_index]
+            .lists
+            .insert(list_id.clone(), ("!stack".to_string(), vec![]));
+
+        self.stack_list = Some(list_id.clone());
+        list_id
+    }
+
+    /// records the signature of every function in the target before any statements
+    /// are compiled, so functions can be called above their declaration
+    fn declare_functions(&mut self, ast: &[Stmt]) -> Result<()> {
+        for statement in ast {
+            let Stmt::FunctionDeclaration(func_name, args, body, return_type, attributes) =
+                statement
+            else {
+                continue;
+            };
+
+            let return_var_name = format!("!func_var_{}", func_name);
+
+            match return_type {
+                Type::Number | Type::String | Type::Bool => {
+                    self.push_var(Vec::new(), return_var_name, return_type.clone());
+                }
+                Type::Table => bail!("function {} cannot return a table", func_name),
+                Type::Void => {}
+            }
+
+            self.return_table
+                .insert(func_name.clone(), return_type.clone());
+
+            let warp = attributes.contains(&Attribute::Warp);
+            let label = attributes.iter().find_map(|attribute| match attribute {
+                Attribute::Label(label) => Some(label),
+                _ => None,
+            });
+
+            let (proc_code, arg_order) = match label {
+                Some(label) => Compiler::proc_code_from_label(func_name, label, args)?,
+                None => {
+                    let mut proc_code = func_name.clone();
+
+                    for (arg_name, arg_type) in args {
+                        proc_code.push_str(match arg_type {
+                            Type::Number | Type::String => " %s",
+                            Type::Bool => " %b",
+                            Type::Table | Type::Void => bail!(
+                                "argument {} of {} cannot be of type {}",
+                                arg_name,
+                                func_name,
+                                format!("{:?}", arg_type).to_lowercase()
+                            ),
+                        });
+                    }
+
+                    (proc_code, (0..args.len()).collect())
+                }
+            };
+
+            self.prototype_table
+                .insert(func_name.clone(), (proc_code, arg_order, warp));
+
+            if attributes.contains(&Attribute::Inline) {
+                if warp || attributes.contains(&Attribute::Recursive) {
+                    bail!(
+                        "function {} cannot be both inline and warp/recursive",
+                        func_name
+                    );
+                }
+
+                if let Some(reason) = optimize::inline_blocker(func_name, body, ast) {
+                    bail!("function {} cannot be inlined as {}", func_name, reason);
+                }
+
+                self.inline_functions
+                    .insert(func_name.clone(), (args.clone(), body.clone()));
+            }
+
+            // inlined functions have no definition, so their arguments don't need ids
+            let arg_ids =
+                if attributes.contains(&Attribute::Inline) && !self.is_remote_function(func_name) {
+                    Vec::new()
+                } else {
+                    args.iter()
+                        .map(|(arg_name, arg_type)| {
+                            (self.gen_arg_id(), arg_name.clone(), arg_type.clone())
+                        })
+                        .collect()
+                };
+            self.arg_table.insert(func_name.clone(), arg_ids);
+        }
+
+        Ok(())
+    }
+
+    /// the locals of `#[recursive]` functions live on the target's one `!stack` list, so two
+    /// scripts running the same recursive function at once would pop each other's frames
+    fn check_shared_stacks(&self, ast: &[Stmt]) -> Result<()> {
+        let bodies: HashMap<&str, &Vec<Stmt>> = ast
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::FunctionDeclaration(func_name, _, body, _, _) => {
+                    Some((func_name.as_str(), body))
+                }
+                _ => None,
+            })
+            .collect();
+
+        // the functions each script calls directly, calls from another target
+        // each run in a script of their own
+        let mut scripts: Vec<Vec<String>> = ast
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::EventHandler(_, body) => Some(optimize::called_functions(body)),
+                _ => None,
+            })
+            .collect();
+
+        for func_name in bodies.keys() {
+            if self.is_remote_function(func_name) {
+                scripts.push(vec![func_name.to_string()]);
+            }
+        }
+
+        // HashMap<FunctionName, ScriptCount>
+        let mut script_counts: HashMap<String, usize> = HashMap::new();
+
+        for mut unvisited in scripts {
+            let mut reachable = HashSet::new();
+
+            while let Some(func_name) = unvisited.pop() {
+                if let Some(body) = bodies.get(func_name.as_str()) {
+                    if reachable.insert(func_name) {
+                        unvisited.extend(optimize::called_functions(body));
+                    }
+                }
+            }
+
+            for func_name in reachable {
+                *script_counts.entry(func_name).or_default() += 1;
+            }
+        }
+
+        for stmt in ast {
+            if let Stmt::FunctionDeclaration(func_name, _, _, _, attributes) = stmt {
+                if attributes.contains(&Attribute::Recursive)
+                    && script_counts.get(func_name).copied().unwrap_or(0) > 1
+                {
+                    bail!(
+                        "recursive function {} is called from more than one script, which would share its stack",
+                        func_name
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// whether a function in the current target is called from another target
+    fn is_remote_function(&self, func_name: &str) -> bool {
+        let qualified_name = format!("{}::{}", self.current_target.0.name, func_name);
+
+        self.remote_functions
+            .iter()
+            .any(|(name, _, _, _, _)| *name == qualified_name)
     }
 
-    fn compile_top_level_statement(&mut self, statement: &Stmt) {
+    fn compile_top_level_statement(&mut self, statement: &Stmt) -> Result<()> {
         match statement {
             // TODO: scope for event handler
             Stmt::EventHandler(event, body) => match event {
@@ -955,30 +2616,81 @@ impl Compiler {
                         flag_id.clone(),
                     );
 
-                    self.compile_body_statements(body, flag_id, None);
+                    self.compile_body_statements(body, flag_id, None)?;
                 }
-                _ => todo!(),
-            },
-            Stmt::FunctionDeclaration(func_name, args, body, return_type) => {
-                let return_var_name = format!("!func_var_{}", func_name);
-                let mut parent_scope = self.scope_path.clone();
-                parent_scope.pop();
+                Event::Hat(hat_name, args) => {
+                    let block = match blocks::lookup(hat_name) {
+                        Some(block) if block.shape == Shape::Hat => block,
+                        _ => bail!("unknown event: {:?}", hat_name),
+                    };
+
+                    let hat_id = self.gen_block_id();
+                    let (inputs, fields) =
+                        self.compile_block_inputs(block, args, hat_id.clone())?;
+
+                    self.next_block_id = if !body.is_empty() {
+                        Some(self.peek_next_block_id())
+                    } else {
+                        None
+                    };
+
+                    self.push_block(
+                        &Block {
+                            opcode: block.opcode.to_string(),
+                            next: self.next_block_id.clone(),
+                            inputs: Some(inputs),
+                            fields,
+                            top_level: Some(true),
+                            ..Block::default()
+                        },
+                        hat_id.clone(),
+                    );
+
+                    self.compile_body_statements(body, hat_id, None)?;
+                }
+                Event::KeyPressed(key) => {
+                    let hat_id = self.gen_block_id();
+
+                    self.next_block_id = if !body.is_empty() {
+                        Some(self.peek_next_block_id())
+                    } else {
+                        None
+                    };
+
+                    self.push_block(
+                        &Block {
+                            opcode: "event_whenkeypressed".to_string(),
+                            next: self.next_block_id.clone(),
+                            fields: Some(
+                                json!({"KEY_OPTION": [Compiler::key_option(key), Value::Null]}),
+                            ),
+                            top_level: Some(true),
+                            ..Block::default()
+                        },
+                        hat_id.clone(),
+                    );
 
+                    self.compile_body_statements(body, hat_id, None)?;
+                }
+            },
+            Stmt::FunctionDeclaration(func_name, _, body, return_type, attributes) => {
+                // the signature was recorded by `declare_functions`
                 let return_var = match return_type {
-                    Type::Number => Some((
-                        return_var_name.clone(),
-                        self.push_var(parent_scope, return_var_name.clone(), Type::Number),
-                    )),
-                    Type::String => Some((
-                        return_var_name.clone(),
-                        self.push_var(parent_scope, return_var_name.clone(), Type::String),
-                    )),
-                    Type::Bool => todo!(),
-                    Type::Table => todo!(),
                     Type::Void => None,
+                    _ => {
+                        let return_var_name = format!("!func_var_{}", func_name);
+                        let (return_var_id, _) =
+                            self.var_table[&Vec::new()][&return_var_name].clone();
+                        Some((return_var_name, return_var_id))
+                    }
                 };
 
-                self.arg_table.insert(func_name.clone(), vec![]);
+                let (proc_code, arg_order, warp) = self.prototype_table[func_name].clone();
+
+                // the definition is only needed when another target calls it
+                if attributes.contains(&Attribute::Inline) && !self.is_remote_function(func_name) {
+                    return Ok(());
+                }
 
                 let prototype_id = self.gen_block_id(); // a
                 let definition_id = self.gen_block_id(); // b
@@ -988,34 +2700,14 @@ impl Compiler {
 
                 let mut arg_blocks: Vec<(String, Block)> = Vec::new();
 
-                let mut proc_code = func_name.clone();
-                let mut argument_ids = String::from("[");
-                let mut argument_names = String::from("[");
-                let mut argument_defaults = String::from("[");
-
-                for (index, (arg_name, arg_type)) in args.iter().enumerate() {
-                    let proc_code_frag = match arg_type {
-                        Type::Number | Type::String => " %s",
-                        Type::Bool => " %b",
-                        Type::Table => todo!(),
-                        Type::Void => todo!(),
-                    };
-
-                    proc_code.push_str(proc_code_frag);
-
-                    if index != 0 {
-                        argument_ids.push_str(",");
-                        argument_names.push_str(",");
-                        argument_defaults.push_str(",");
-                    }
-
-                    let arg_id = self.gen_arg_id().to_string();
+                // Vec<(ArgId, ArgName, ArgDefault)>
+                let mut arg_mutations = Vec::new();
 
+                for (arg_id, arg_name, arg_type) in self.arg_table[func_name].clone() {
                     let arg_default = match arg_type {
                         Type::Number | Type::String => "",
                         Type::Bool => "false",
-                        Type::Table => todo!(),
-                        Type::Void => todo!(),
+                        Type::Table | Type::Void => unreachable!("rejected by declare_functions"),
                     };
 
                     let arg_block_id = self.gen_block_id();
@@ -1024,8 +2716,7 @@ impl Compiler {
                     let opcode = match arg_type {
                         Type::Number | Type::String => "argument_reporter_string_number",
                         Type::Bool => "argument_reporter_boolean",
-                        Type::Table => todo!(),
-                        Type::Void => todo!(),
+                        Type::Table | Type::Void => unreachable!("rejected by declare_functions"),
                     };
 
                     let arg_block = Block {
@@ -1039,13 +2730,25 @@ impl Compiler {
 
                     arg_blocks.push((arg_block_id, arg_block));
 
+                    arg_mutations.push((arg_id, arg_name, arg_default));
+                }
+
+                let mut argument_ids = String::from("[");
+                let mut argument_names = String::from("[");
+                let mut argument_defaults = String::from("[");
+
+                for (index, position) in arg_order.iter().enumerate() {
+                    if index != 0 {
+                        argument_ids.push_str(",");
+                        argument_names.push_str(",");
+                        argument_defaults.push_str(",");
+                    }
+
+                    let (arg_id, arg_name, arg_default) = &arg_mutations[*position];
+
                     argument_ids.push_str(&format!("\"{}\"", arg_id));
                     argument_names.push_str(&format!("\"{}\"", arg_name));
                     argument_defaults.push_str(&format!("\"{}\"", arg_default));
-
-                    // TODO: error handling please???
-                    let function_table = self.arg_table.get_mut(func_name).unwrap();
-                    function_table.push((arg_id, arg_name.to_string()));
                 }
 
                 argument_ids.push_str("]");
@@ -1078,7 +2781,7 @@ impl Compiler {
                         argumentids: Some(argument_ids),
                         argumentnames: Some(argument_names),
                         argumentdefaults: Some(argument_defaults),
-                        warp: Some("false".to_string()),
+                        warp: Some(warp.to_string()),
                         ..Mutation::default()
                     }),
                     ..Default::default()
@@ -1091,18 +2794,133 @@ impl Compiler {
                     self.push_block(&block, id);
                 }
 
-                self.compile_body_statements(body, definition_id.clone(), return_var);
+                self.current_function = Some(func_name.clone());
+
+                if attributes.contains(&Attribute::Recursive) {
+                    let body = self.lower_calls_deep(body)?;
+                    let frame_size = Compiler::count_locals(&body);
+                    let body = Compiler::lower_stack_frame(&body, frame_size, &return_var, true)?;
+
+                    self.stack_frame = Some((frame_size, 0));
+                    self.compile_body_statements(&body, definition_id.clone(), return_var)?;
+                    self.stack_frame = None;
+                } else {
+                    self.compile_body_statements(body, definition_id.clone(), return_var)?;
+                }
+
+                self.current_function = None;
             }
-            _ => panic!("statement type: {:#?} cannot be top-level", statement),
+            _ => bail!("statement type: {:#?} cannot be top-level", statement),
         }
+
+        Ok(())
     }
 
-    fn push_block(&mut self, block: &Block, id: String) {
-        println!(
-            "BLOCK PUSHED [{}] -> [{:?}]: {}",
-            id, self.next_block_id, block.opcode
-        );
+    /// the name scratch gives a key in its key menus
+    fn key_option(key: &Key) -> String {
+        match key {
+            Key::Any => "any".to_string(),
+            Key::Space => "space".to_string(),
+            Key::Up => "up arrow".to_string(),
+            Key::Down => "down arrow".to_string(),
+            Key::Left => "left arrow".to_string(),
+            Key::Right => "right arrow".to_string(),
+            Key::Char(char) => char.to_string(),
+        }
+    }
+
+    /// turns a label like "move %x steps towards %target" into a proccode,
+    /// also returns the position of each argument in the order they appear in the label
+    fn proc_code_from_label(
+        func_name: &str,
+        label: &str,
+        args: &[(String, Type)],
+    ) -> Result<(String, Vec<usize>)> {
+        let mut proc_code = String::new();
+        let mut arg_order: Vec<usize> = Vec::new();
+        let mut chars = label.chars().peekable();
+
+        while let Some(char) = chars.next() {
+            if char != '%' {
+                proc_code.push(char);
+                continue;
+            }
+
+            // `%%` is a literal percent sign
+            if chars.peek() == Some(&'%') {
+                chars.next();
+
+                // scratch reads `%s`, `%n` and `%b` as inputs unless the `%` is escaped
+                if matches!(chars.peek(), Some('s' | 'n' | 'b')) {
+                    proc_code.push('\\');
+                }
+
+                proc_code.push('%');
+                continue;
+            }
+
+            let mut arg_name = String::new();
+            while let Some(&char) = chars.peek() {
+                if !(char.is_ascii_alphanumeric() || char == '_') {
+                    break;
+                }
+
+                arg_name.push(char);
+                chars.next();
+            }
+
+            // so is a `%` that isn't followed by an argument name, like in "10% bigger"
+            if arg_name.is_empty() {
+                proc_code.push('%');
+                continue;
+            }
 
+            let Some(position) = args.iter().position(|(name, _)| *name == arg_name) else {
+                bail!(
+                    "label of {} references unknown argument: %{}",
+                    func_name,
+                    arg_name
+                );
+            };
+
+            if arg_order.contains(&position) {
+                bail!(
+                    "argument {} appears more than once in label of {}",
+                    arg_name,
+                    func_name
+                );
+            }
+
+            proc_code.push_str(match args[position].1 {
+                Type::Number | Type::String => "%s",
+                Type::Bool => "%b",
+                Type::Table | Type::Void => bail!(
+                    "argument %{} of type {} cannot appear in the label of {}",
+                    arg_name,
+                    format!("{:?}", args[position].1).to_lowercase(),
+                    func_name
+                ),
+            });
+            arg_order.push(position);
+        }
+
+        if let Some((arg_name, _)) = args
+            .iter()
+            .enumerate()
+            .find(|(position, _)| !arg_order.contains(position))
+            .map(|(_, arg)| arg)
+        {
+            bail!(
+                "argument {} is missing from label of {}",
+                arg_name,
+                func_name
+            );
+        }
+
+        Ok((proc_code, arg_order))
+    }
+
+    fn push_block(&mut self, block: &Block, id: String) {
         self.project.targets[self.target_index]
             .blocks
             .insert(id, block.clone());
@@ -1147,19 +2965,25 @@ impl Compiler {
             .unwrap()
             .insert(var_name.clone(), (var_id.clone(), var_type.clone()));
 
+        // locals of recursive functions live on the stack instead
+        if let Some((_, next_slot)) = &mut self.stack_frame {
+            self.stack_slots.insert(var_id.clone(), *next_slot);
+            *next_slot += 1;
+            return var_id;
+        }
+
         self.project.targets[self.target_index]
             .variables
             .insert(var_id.clone(), json!([var_name, 0]));
 
-        println!("{:#?} - {:#?}", scope_path, var_name);
         var_id
     }
 
-    fn get_var_id(&self, scope_path: Vec<String>, var_name: String) -> String {
-        self.get_var(scope_path, var_name).0
+    fn get_var_id(&self, scope_path: Vec<String>, var_name: String) -> Result<String> {
+        Ok(self.get_var(scope_path, var_name)?.0)
     }
 
-    fn get_var(&self, scope_path: Vec<String>, var_name: String) -> (String, Type) {
+    fn get_var(&self, scope_path: Vec<String>, var_name: String) -> Result<(String, Type)> {
         let mut scope_path = scope_path.clone();
 
         // this is really bad
@@ -1169,7 +2993,7 @@ impl Compiler {
             if scope.is_none() {
                 match scope_path.pop() {
                     Some(_) => continue,
-                    None => panic!("variable {:?} not found in scope", var_name),
+                    None => bail!("variable {:?} not found in scope", var_name),
                 }
             }
 
@@ -1180,36 +3004,62 @@ impl Compiler {
             if var.is_none() {
                 match scope_path.pop() {
                     Some(_) => continue,
-                    None => panic!("variable {:?} not found in scope", var_name),
+                    None => bail!("variable {:?} not found in scope", var_name),
                 }
             }
 
-            return var.unwrap().clone();
+            return Ok(var.unwrap().clone());
         }
     }
 
     fn var_exists(&self, scope_path: Vec<String>, var_name: String) -> bool {
-        let scope_table = self.var_table.get(&scope_path);
-        let scoped_table = if let Some(table) = scope_table {
-            table
-        } else {
-            return false;
-        };
+        let mut scope_path = scope_path;
+
+        loop {
+            if let Some(table) = self.var_table.get(&scope_path) {
+                if table.contains_key(&var_name) {
+                    return true;
+                }
+            }
 
-        match scoped_table.get(&var_name) {
-            Some(_) => true,
-            None => false,
+            if scope_path.pop().is_none() {
+                return false;
+            }
         }
     }
 
-    // DO NOT TOUCH LMFAO
+    /// the type of an argument of the function currently being compiled
+    fn arg_type(&self, arg_name: &str) -> Option<Type> {
+        let func_name = self.current_function.as_ref()?;
+
+        self.arg_table
+            .get(func_name)?
+            .iter()
+            .find(|(_, name, _)| name == arg_name)
+            .map(|(_, _, arg_type)| arg_type.clone())
+    }
+
     fn gen_block_id(&mut self) -> String {
         self.block_id += 1;
-        ('a'..='z').into_iter().collect::<Vec<char>>()[self.block_id - 1].to_string()
+        Compiler::block_id_from_index(self.block_id - 1)
     }
 
     fn peek_next_block_id(&self) -> String {
-        ('a'..='z').into_iter().collect::<Vec<char>>()[self.block_id].to_string()
+        Compiler::block_id_from_index(self.block_id)
+    }
+
+    // a, b, ..., z, aa, ab, ...
+    fn block_id_from_index(index: usize) -> String {
+        let mut index = index + 1;
+        let mut id = Vec::new();
+
+        while index > 0 {
+            index -= 1;
+            id.push((b'a' + (index % 26) as u8) as char);
+            index /= 26;
+        }
+
+        id.iter().rev().collect()
     }
 
     fn gen_arg_id(&mut self) -> String {
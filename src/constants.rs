@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::{bail, Result},
+    parser::{Event, Expr, Stmt},
+    token::Type,
+};
+
+/// removes `const` and `enum` declarations from a target's script and replaces every use
+/// of them with their value, so no scratch variables are created for them,
+/// enum variants (`Dir::Up`) are replaced with their name as a string (`"Up"`)
+pub fn inline_constants(ast: Vec<Stmt>) -> Result<Vec<Stmt>> {
+    // HashMap<ConstName, Value>
+    let mut constants: HashMap<String, Expr> = HashMap::new();
+    let mut statements = Vec::new();
+
+    for stmt in ast {
+        match stmt {
+            Stmt::Constant(const_name, const_type, value) => {
+                let value = inline_expr(&value, &constants)?;
+
+                let value_type = match value {
+                    Expr::Number(_) => Type::Number,
+                    Expr::String(_) => Type::String,
+                    Expr::Bool(_) => Type::Bool,
+                    _ => bail!(
+                        "constant {} must be a literal, recieved: {:?}",
+                        const_name,
+                        value
+                    ),
+                };
+
+                if value_type != const_type {
+                    bail!(
+                        "constant {} should be of type: {:?}, recieved type: {:?}",
+                        const_name,
+                        const_type,
+                        value_type
+                    );
+                }
+
+                declare(&mut constants, const_name, value)?;
+            }
+            Stmt::Enum(enum_name, variants) => {
+                for variant in variants {
+                    declare(
+                        &mut constants,
+                        format!("{}::{}", enum_name, variant),
+                        Expr::String(variant),
+                    )?;
+                }
+            }
+            _ => statements.push(stmt),
+        }
+    }
+
+    inline_body(&statements, &constants)
+}
+
+fn declare(constants: &mut HashMap<String, Expr>, name: String, value: Expr) -> Result<()> {
+    if constants.contains_key(&name) {
+        bail!("constant {} is declared more than once", name);
+    }
+
+    constants.insert(name, value);
+    Ok(())
+}
+
+fn inline_body(body: &[Stmt], constants: &HashMap<String, Expr>) -> Result<Vec<Stmt>> {
+    body.iter()
+        .map(|stmt| inline_stmt(stmt, constants))
+        .collect()
+}
+
+fn inline_args(args: &[Expr], constants: &HashMap<String, Expr>) -> Result<Vec<Expr>> {
+    args.iter().map(|arg| inline_expr(arg, constants)).collect()
+}
+
+fn assert_not_constant(name: &str, constants: &HashMap<String, Expr>) -> Result<()> {
+    if constants.contains_key(name) {
+        bail!("{} is a constant and cannot be assigned to", name);
+    }
+
+    Ok(())
+}
+
+fn inline_stmt(stmt: &Stmt, constants: &HashMap<String, Expr>) -> Result<Stmt> {
+    let stmt = match stmt {
+        Stmt::VariableDeclaration(var_name, var_type, expr) => {
+            assert_not_constant(var_name, constants)?;
+            Stmt::VariableDeclaration(
+                var_name.clone(),
+                var_type.clone(),
+                inline_expr(expr, constants)?,
+            )
+        }
+        Stmt::VariableAssignment(var_name, expr) => {
+            assert_not_constant(var_name, constants)?;
+            Stmt::VariableAssignment(var_name.clone(), inline_expr(expr, constants)?)
+        }
+        Stmt::VariableMutation(var_name, op, expr) => {
+            assert_not_constant(var_name, constants)?;
+            Stmt::VariableMutation(var_name.clone(), op.clone(), inline_expr(expr, constants)?)
+        }
+        Stmt::FunctionDeclaration(func_name, args, body, return_type, attributes) => {
+            for (arg_name, _) in args {
+                assert_not_constant(arg_name, constants)?;
+            }
+
+            Stmt::FunctionDeclaration(
+                func_name.clone(),
+                args.clone(),
+                inline_body(body, constants)?,
+                return_type.clone(),
+                attributes.clone(),
+            )
+        }
+        Stmt::EventHandler(event, body) => {
+            let event = match event {
+                Event::Hat(hat_name, args) => {
+                    Event::Hat(hat_name.clone(), inline_args(args, constants)?)
+                }
+                _ => event.clone(),
+            };
+
+            Stmt::EventHandler(event, inline_body(body, constants)?)
+        }
+        Stmt::FunctionCall(func_name, args) => {
+            Stmt::FunctionCall(func_name.clone(), inline_args(args, constants)?)
+        }
+        Stmt::If(cond, body_true, body_false) => Stmt::If(
+            inline_expr(cond, constants)?,
+            inline_body(body_true, constants)?,
+            body_false
+                .as_ref()
+                .map(|body_false| inline_body(body_false, constants))
+                .transpose()?,
+        ),
+        Stmt::While(cond, body) => {
+            Stmt::While(inline_expr(cond, constants)?, inline_body(body, constants)?)
+        }
+        Stmt::Return(expr) => Stmt::Return(inline_expr(expr, constants)?),
+        _ => stmt.clone(),
+    };
+
+    Ok(stmt)
+}
+
+fn inline_expr(expr: &Expr, constants: &HashMap<String, Expr>) -> Result<Expr> {
+    let expr = match expr {
+        Expr::Identifier(ident) => match constants.get(ident) {
+            Some(value) => value.clone(),
+            None if ident.contains("::") => bail!("enum variant {} does not exist", ident),
+            None => expr.clone(),
+        },
+        Expr::Binary(left, op, right) => Expr::Binary(
+            Box::new(inline_expr(left, constants)?),
+            op.clone(),
+            Box::new(inline_expr(right, constants)?),
+        ),
+        Expr::FunctionCall(func_name, args) => {
+            Expr::FunctionCall(func_name.clone(), inline_args(args, constants)?)
+        }
+        Expr::Map(entries) => Expr::Map(
+            entries
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), inline_expr(value, constants)?)))
+                .collect::<Result<_>>()?,
+        ),
+        _ => expr.clone(),
+    };
+
+    Ok(expr)
+}
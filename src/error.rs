@@ -1,10 +1,87 @@
-use crate::token::Token;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
 
-pub fn error(line: usize, string: String) {
-    println!("[{:?}] {:?}", line, string);
+use crate::makefile::MakefileError;
+
+/// a problem that stopped a project from compiling
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// the file the problem is in, if known
+    pub path: Option<PathBuf>,
+    /// starting from 1, only known for problems found while lexing and parsing
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+pub type Result<T> = std::result::Result<T, Diagnostic>;
+
+/// returns early with a diagnostic, formatted like `format!`
+macro_rules! bail {
+    ($($arg:tt)*) => {
+        return Err($crate::error::Diagnostic::new(format!($($arg)*)))
+    };
+}
+
+pub(crate) use bail;
+
+impl Diagnostic {
+    pub(crate) fn new(message: String) -> Diagnostic {
+        Diagnostic {
+            path: None,
+            line: None,
+            message,
+        }
+    }
+
+    pub(crate) fn at_line(line: usize, message: String) -> Diagnostic {
+        Diagnostic {
+            line: Some(line),
+            ..Diagnostic::new(message)
+        }
+    }
+
+    /// sets the file the problem is in, unless a more specific one is already known,
+    /// like an imported file
+    pub(crate) fn in_file(self, path: &Path) -> Diagnostic {
+        Diagnostic {
+            path: self.path.or_else(|| Some(path.to_path_buf())),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{}", path.display())?;
+
+            if let Some(line) = self.line {
+                write!(f, ":{}", line)?;
+            }
+
+            write!(f, ": ")?;
+        }
+
+        write!(f, "{}", self.message)
+    }
 }
 
-pub fn parse_error(tok: Token, string: String) {
-    println!("PARSE ERROR: [{:?}] {:?}", tok, string);
-    todo!();
+impl std::error::Error for Diagnostic {}
+
+impl From<MakefileError> for Diagnostic {
+    fn from(error: MakefileError) -> Self {
+        let message = if error.key.is_empty() {
+            error.message
+        } else {
+            format!("{}: {}", error.key, error.message)
+        };
+
+        Diagnostic {
+            path: Some(error.path),
+            line: error.line,
+            message,
+        }
+    }
 }
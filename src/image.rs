@@ -0,0 +1,152 @@
+/// the formats allowed by `dataFormat` in the sb3 schema
+pub const COSTUME_FORMATS: [&str; 6] = ["png", "svg", "jpeg", "jpg", "bmp", "gif"];
+
+pub fn is_bitmap(data_format: &str) -> bool {
+    data_format != "svg"
+}
+
+/// (Width, Height) in the units scratch measures the rotation center in,
+/// that is svg user units or bitmap pixels, `None` if the size can't be read
+pub fn image_size(data_format: &str, content: &[u8]) -> Option<(f64, f64)> {
+    match data_format {
+        "svg" => svg_size(&String::from_utf8_lossy(content)),
+        "png" => png_size(content),
+        "jpg" | "jpeg" => jpeg_size(content),
+        "gif" => gif_size(content),
+        "bmp" => bmp_size(content),
+        _ => None,
+    }
+}
+
+/// uses `width` and `height` if they're plain numbers, otherwise the size of the `viewBox`
+fn svg_size(svg: &str) -> Option<(f64, f64)> {
+    let start = svg.find("<svg")?;
+    let end = start + svg[start..].find('>')?;
+    let tag = &svg[start..end];
+
+    let length = |name: &str| {
+        svg_attribute(tag, name)
+            .map(|value| value.trim().trim_end_matches("px"))
+            .and_then(|value| value.parse::<f64>().ok())
+    };
+
+    if let (Some(width), Some(height)) = (length("width"), length("height")) {
+        return Some((width, height));
+    }
+
+    let view_box: Vec<f64> = svg_attribute(tag, "viewBox")?
+        .split(|char: char| char.is_whitespace() || char == ',')
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+
+    match view_box[..] {
+        [_, _, width, height] => Some((width, height)),
+        _ => None,
+    }
+}
+
+fn svg_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let pattern = format!("{}=", name);
+    let mut search_from = 0;
+
+    while let Some(offset) = tag[search_from..].find(&pattern) {
+        let index = search_from + offset;
+        search_from = index + pattern.len();
+
+        // skip attributes that merely end in the name, like `stroke-width`
+        if !tag[..index].ends_with(char::is_whitespace) {
+            continue;
+        }
+
+        let rest = &tag[search_from..];
+        let quote = rest.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            return None;
+        }
+
+        let rest = &rest[1..];
+        return rest.find(quote).map(|end| &rest[..end]);
+    }
+
+    None
+}
+
+fn png_size(content: &[u8]) -> Option<(f64, f64)> {
+    if !content.starts_with(b"\x89PNG\r\n\x1a\n") || &content.get(12..16)? != b"IHDR" {
+        return None;
+    }
+
+    let width = u32::from_be_bytes(content.get(16..20)?.try_into().ok()?);
+    let height = u32::from_be_bytes(content.get(20..24)?.try_into().ok()?);
+
+    Some((width as f64, height as f64))
+}
+
+/// walks the segments until the start of frame, which holds the size
+fn jpeg_size(content: &[u8]) -> Option<(f64, f64)> {
+    if !content.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let mut index = 2;
+
+    loop {
+        if *content.get(index)? != 0xFF {
+            return None;
+        }
+
+        let marker = *content.get(index + 1)?;
+        match marker {
+            // padding before a marker
+            0xFF => index += 1,
+            // markers without a length
+            0x01 | 0xD0..=0xD8 => index += 2,
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                let height =
+                    u16::from_be_bytes([*content.get(index + 5)?, *content.get(index + 6)?]);
+                let width =
+                    u16::from_be_bytes([*content.get(index + 7)?, *content.get(index + 8)?]);
+
+                return Some((width as f64, height as f64));
+            }
+            _ => {
+                let length =
+                    u16::from_be_bytes([*content.get(index + 2)?, *content.get(index + 3)?]);
+                index += 2 + length as usize;
+            }
+        }
+    }
+}
+
+fn gif_size(content: &[u8]) -> Option<(f64, f64)> {
+    if !content.starts_with(b"GIF87a") && !content.starts_with(b"GIF89a") {
+        return None;
+    }
+
+    let width = u16::from_le_bytes(content.get(6..8)?.try_into().ok()?);
+    let height = u16::from_le_bytes(content.get(8..10)?.try_into().ok()?);
+
+    Some((width as f64, height as f64))
+}
+
+fn bmp_size(content: &[u8]) -> Option<(f64, f64)> {
+    if !content.starts_with(b"BM") {
+        return None;
+    }
+
+    let header_size = u32::from_le_bytes(content.get(14..18)?.try_into().ok()?);
+
+    // the old OS/2 header uses 16 bit sizes
+    if header_size == 12 {
+        let width = u16::from_le_bytes(content.get(18..20)?.try_into().ok()?);
+        let height = u16::from_le_bytes(content.get(20..22)?.try_into().ok()?);
+        return Some((width as f64, height as f64));
+    }
+
+    let width = i32::from_le_bytes(content.get(18..22)?.try_into().ok()?);
+    // negative when the rows are stored top to bottom
+    let height = i32::from_le_bytes(content.get(22..26)?.try_into().ok()?);
+
+    Some((width.unsigned_abs() as f64, height.unsigned_abs() as f64))
+}
@@ -0,0 +1,105 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::{bail, Result},
+    files::Files,
+    lexer::Lexer,
+    parser::{Parser, Stmt},
+};
+
+/// replaces every `import "path";` in a target's script with the functions and constants declared in that file,
+/// each file is only merged into a target once, no matter how many times it's imported
+pub fn resolve_imports(
+    ast: Vec<Stmt>,
+    project_path: &Path,
+    files: &dyn Files,
+) -> Result<Vec<Stmt>> {
+    let mut imported: HashSet<PathBuf> = HashSet::new();
+    let resolved = resolve(ast, project_path, files, &mut Vec::new(), &mut imported)?;
+
+    let mut func_names: Vec<&String> = Vec::new();
+    for stmt in &resolved {
+        if let Stmt::FunctionDeclaration(func_name, _, _, _, _) = stmt {
+            if func_names.contains(&func_name) {
+                bail!("function {} is declared more than once", func_name);
+            }
+
+            func_names.push(func_name);
+        }
+    }
+
+    Ok(resolved)
+}
+
+fn resolve(
+    ast: Vec<Stmt>,
+    project_path: &Path,
+    files: &dyn Files,
+    // files currently being imported, used to detect cycles
+    import_stack: &mut Vec<PathBuf>,
+    imported: &mut HashSet<PathBuf>,
+) -> Result<Vec<Stmt>> {
+    let mut resolved = Vec::new();
+
+    for stmt in ast {
+        let path = match stmt {
+            Stmt::Import(path) => path,
+            _ => {
+                resolved.push(stmt);
+                continue;
+            }
+        };
+
+        let file_path = project_path.join(&path);
+        let file_path = match files.canonicalize(&file_path) {
+            Ok(file_path) => file_path,
+            Err(_) => bail!("imported file {} does not exist", file_path.display()),
+        };
+
+        if import_stack.contains(&file_path) {
+            let cycle: Vec<String> = import_stack
+                .iter()
+                .chain([&file_path])
+                .map(|path| path.display().to_string())
+                .collect();
+
+            bail!("import cycle: {}", cycle.join(" -> "));
+        }
+
+        if !imported.insert(file_path.clone()) {
+            continue;
+        }
+
+        let script = match files.read_to_string(&file_path) {
+            Ok(script) => script,
+            Err(error) => bail!("could not read {}: {}", file_path.display(), error),
+        };
+        let imported_ast = Lexer::new(&script)
+            .lex()
+            .and_then(|tokens| Parser::new(tokens).parse())
+            .map_err(|error| error.in_file(&file_path))?;
+
+        import_stack.push(file_path.clone());
+        let imported_ast = resolve(imported_ast, project_path, files, import_stack, imported)
+            .map_err(|error| error.in_file(&file_path))?;
+        import_stack.pop();
+
+        for stmt in imported_ast {
+            match stmt {
+                Stmt::FunctionDeclaration(_, _, _, _, _)
+                | Stmt::Constant(_, _, _)
+                | Stmt::Enum(_, _) => resolved.push(stmt),
+                _ => bail!(
+                    "only functions and constants can be imported, found in {}: {:#?}",
+                    path,
+                    stmt
+                ),
+            }
+        }
+    }
+
+    Ok(resolved)
+}
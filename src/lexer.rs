@@ -1,4 +1,4 @@
-use crate::error::error;
+use crate::error::{Diagnostic, Result};
 use crate::token::{Operator, Token, TokenType, Type};
 
 pub struct Lexer {
@@ -132,9 +132,12 @@ impl Lexer {
             "for" => Some(TokenType::For),
             "function" => Some(TokenType::Function),
             "event" => Some(TokenType::Event),
+            "import" => Some(TokenType::Import),
             "true" => Some(TokenType::Bool(true)),
             "false" => Some(TokenType::Bool(false)),
             "let" => Some(TokenType::Let),
+            "const" => Some(TokenType::Const),
+            "enum" => Some(TokenType::Enum),
             "number" => Some(TokenType::Type(Type::Number)),
             "string" => Some(TokenType::Type(Type::String)),
             "bool" => Some(TokenType::Type(Type::Bool)),
@@ -144,7 +147,9 @@ impl Lexer {
         }
     }
 
-    fn lex_string(&mut self) {
+    fn lex_string(&mut self) -> Result<()> {
+        let start_line = self.line;
+
         while self.peek() != '"' && !self.is_at_end() {
             if self.peek() == '\n' {
                 self.line += 1
@@ -153,13 +158,17 @@ impl Lexer {
         }
 
         if self.is_at_end() {
-            error(self.line, "Unterminted string.".to_string());
+            return Err(Diagnostic::at_line(
+                start_line,
+                "unterminated string".to_string(),
+            ));
         }
 
         self.advance();
 
         let string: String = self.source[self.start + 1..self.current - 1].to_string();
         self.add_token(TokenType::String(string));
+        Ok(())
     }
 
     // this might be hacky, maybe in the future
@@ -198,7 +207,7 @@ impl Lexer {
         }
     }
 
-    pub fn lex_token(&mut self) {
+    pub fn lex_token(&mut self) -> Result<()> {
         let char = self.advance();
 
         match char {
@@ -211,7 +220,8 @@ impl Lexer {
             ',' => self.add_token(TokenType::Comma),
             '.' => self.add_token(TokenType::Dot),
             ';' => self.add_token(TokenType::Semicolon),
-            ':' => self.add_token(TokenType::Colon),
+            ':' => self.add_token_cond(':', TokenType::ColonColon, TokenType::Colon),
+            '#' => self.add_token(TokenType::Hash),
             '+' => self.add_token_cond(
                 '=',
                 TokenType::Operator(Operator::PlusEqual),
@@ -264,26 +274,31 @@ impl Lexer {
                     );
                 }
             }
-            '"' => self.lex_string(),
+            '"' => self.lex_string()?,
             '\t' | ' ' => {}
             '\n' => self.line += 1,
             '0'..='9' => self.lex_number(),
             'a'..='z' | 'A'..='Z' | '_' => self.lex_identifier(),
             _ => {
                 if !self.peek().is_ascii_whitespace() {
-                    error(self.line, format!("Unexpected character: {:?}", char))
+                    return Err(Diagnostic::at_line(
+                        self.line,
+                        format!("unexpected character: {:?}", char),
+                    ));
                 }
             }
         }
+
+        Ok(())
     }
 
-    pub fn lex(&mut self) -> Vec<Token> {
+    pub fn lex(&mut self) -> Result<Vec<Token>> {
         while !self.is_at_end() {
             self.start = self.current;
-            self.lex_token();
+            self.lex_token()?;
         }
 
-        self.add_token(TokenType::EOF);
-        return self.tokens.clone();
+        self.add_token(TokenType::Eof);
+        return Ok(self.tokens.clone());
     }
 }
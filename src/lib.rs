@@ -0,0 +1,200 @@
+#![recursion_limit = "256"]
+//! compiles scuff projects into scratch 3 projects
+//!
+//! ```no_run
+//! let compilation = scuff::compile("project.toml").unwrap();
+//! compilation.package("project.sb3".as_ref()).unwrap();
+//! ```
+//!
+//! projects that aren't on disk can be compiled from [`MemoryFiles`] with [`compile_from`]
+
+mod assets;
+mod blocks;
+mod compiler;
+mod constants;
+mod error;
+mod files;
+mod image;
+mod import;
+mod lexer;
+mod makefile;
+mod optimize;
+mod packager;
+mod parser;
+mod project;
+mod scaffold;
+mod token;
+mod validate;
+
+#[cfg(test)]
+mod compilation_test;
+
+use std::{
+    fmt,
+    fmt::Write as _,
+    io::{Seek, Write},
+    path::Path,
+};
+
+pub use crate::{
+    blocks::docs,
+    error::Diagnostic,
+    files::{Files, MemoryFiles},
+    packager::PackageError,
+    scaffold::{add_sprite, new_project},
+};
+
+use crate::{
+    assets::AssetStore,
+    compiler::Compiler,
+    files::Disk,
+    lexer::Lexer,
+    makefile::{Compression, MakefileData, TargetData},
+    packager::PackageOptions,
+    parser::{Parser, Stmt},
+    project::Project,
+};
+
+/// a compiled project and the assets it uses, ready to be packaged
+#[derive(Clone)]
+pub struct Compilation {
+    pub(crate) project: Project,
+    pub(crate) assets: AssetStore,
+    pub(crate) options: PackageOptions,
+}
+
+// project.json is too large to be useful in debug output
+impl fmt::Debug for Compilation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Compilation")
+            .field("targets", &self.project.targets.len())
+            .field("assets", &self.assets)
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Compilation {
+    /// checks project.json against the sb3 schema
+    pub fn validate(&self) -> Result<(), Vec<Diagnostic>> {
+        validate::validate_project(&self.project)
+            .map_err(|errors| errors.into_iter().map(Diagnostic::new).collect())
+    }
+
+    /// deflates the sb3 like `--release` does, unless project.toml sets `compression`
+    pub fn release(&mut self) {
+        self.options.compression.get_or_insert(Compression::Deflate);
+    }
+
+    /// project.json, pretty printed
+    pub fn project_json(&self) -> String {
+        serde_json::to_string_pretty(&self.project).unwrap()
+    }
+
+    /// writes the sb3 to anything seekable, like a file or a `Cursor<Vec<u8>>`
+    pub fn write_sb3<W: Write + Seek>(&self, writer: W) -> Result<(), PackageError> {
+        packager::write_project(writer, &self.project, &self.assets, self.options)
+    }
+
+    /// writes the sb3 to `output_path`, replacing it only once the whole project is written
+    pub fn package(&self, output_path: &Path) -> Result<(), PackageError> {
+        packager::package_project(&self.project, &self.assets, self.options, output_path)
+    }
+}
+
+/// compiles the project described by the project.toml at `makefile_path`
+pub fn compile(makefile_path: impl AsRef<Path>) -> Result<Compilation, Vec<Diagnostic>> {
+    compile_from(makefile_path, &Disk)
+}
+
+/// compiles a project read from `files`, paths in project.toml and imports
+/// are relative to the directory of `makefile_path`
+pub fn compile_from(
+    makefile_path: impl AsRef<Path>,
+    files: &dyn Files,
+) -> Result<Compilation, Vec<Diagnostic>> {
+    let makefile = load_makefile(makefile_path.as_ref(), files)?;
+    build(makefile, files).map_err(|error| vec![error])
+}
+
+/// the tokens of every target, for debugging the lexer
+pub fn emit_tokens(makefile_path: impl AsRef<Path>) -> Result<String, Vec<Diagnostic>> {
+    let makefile = load_makefile(makefile_path.as_ref(), &Disk)?;
+    let mut output = String::new();
+
+    for target in &makefile.targets {
+        let tokens = Lexer::new(&target.script)
+            .lex()
+            .map_err(|error| vec![error.in_file(&target.script_path)])?;
+        writeln!(output, "// {}\n{:#?}", target.name, tokens).unwrap();
+    }
+
+    Ok(output)
+}
+
+/// the syntax tree of every target after imports and constants are resolved,
+/// for debugging the parser
+pub fn emit_ast(makefile_path: impl AsRef<Path>) -> Result<String, Vec<Diagnostic>> {
+    let makefile = load_makefile(makefile_path.as_ref(), &Disk)?;
+    let mut output = String::new();
+
+    for (target, ast) in parse_makefile(&makefile, &Disk).map_err(|error| vec![error])? {
+        writeln!(output, "// {}\n{:#?}", target.name, ast).unwrap();
+    }
+
+    Ok(output)
+}
+
+/// reads project.toml and every file it lists
+fn load_makefile(makefile_path: &Path, files: &dyn Files) -> Result<MakefileData, Vec<Diagnostic>> {
+    MakefileData::parse_from(makefile_path.to_path_buf(), files)
+        .map_err(|errors| errors.into_iter().map(Diagnostic::from).collect())
+}
+
+fn build(makefile: MakefileData, files: &dyn Files) -> error::Result<Compilation> {
+    let assets = AssetStore::new(&makefile.targets);
+    let targets = parse_makefile(&makefile, files)?;
+
+    let mut compiler = Compiler::new(targets, makefile.extensions);
+    let options = PackageOptions {
+        compression: makefile.compression,
+        compression_level: makefile.compression_level,
+    };
+
+    Ok(Compilation {
+        project: compiler.compile(&assets)?.clone(),
+        assets,
+        options,
+    })
+}
+
+/// lexes and parses every target, then resolves imports and constants
+fn parse_makefile(
+    makefile: &MakefileData,
+    files: &dyn Files,
+) -> error::Result<Vec<(TargetData, Vec<Stmt>)>> {
+    let mut targets: Vec<(TargetData, Vec<Stmt>)> = vec![];
+
+    for target in &makefile.targets {
+        let parsed = parse_target(target, &makefile.project_path, files)
+            .map_err(|error| error.in_file(&target.script_path))?;
+        targets.push((target.clone(), parsed));
+    }
+
+    if makefile.optimize {
+        Ok(optimize::optimize(targets))
+    } else {
+        Ok(targets)
+    }
+}
+
+fn parse_target(
+    target: &TargetData,
+    project_path: &Path,
+    files: &dyn Files,
+) -> error::Result<Vec<Stmt>> {
+    let tokens = Lexer::new(&target.script).lex()?;
+    let parsed = Parser::new(tokens).parse()?;
+    let parsed = import::resolve_imports(parsed, project_path, files)?;
+    constants::inline_constants(parsed)
+}
@@ -1,61 +1,118 @@
-#![recursion_limit = "256"]
-mod compilation_test;
-mod compiler;
-mod error;
-mod lexer;
-mod makefile;
-mod packager;
-mod parser;
-mod project;
-mod token;
-mod validate;
-
-use project::Project;
-
-use crate::{
-    compiler::Compiler,
-    lexer::Lexer,
-    makefile::{MakefileData, TargetData},
-    packager::package_project,
-    parser::{Parser, Stmt},
-    validate::validate_project,
-};
+mod cli;
+
+use scuff::Diagnostic;
 
-use std::env;
+use crate::cli::{Cli, Command, Verbosity};
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    assert!(args.len() > 1, "Expected file path.");
-    let (project, targets) = compile_project(args[1].clone());
-
-    println!("{}", serde_json::to_string_pretty(&project).unwrap());
-    validate_project(&project);
-    package_project(&project, targets, "project.sb3".into());
-    println!("project written to: project.sb3");
+    let args: Vec<String> = env::args().skip(1).collect();
+    process::exit(exit_code(&args));
 }
 
-pub fn compile_project(makefile_path: String) -> (Project, Vec<(TargetData, Vec<Stmt>)>) {
-    let makefile = MakefileData::parse(makefile_path.clone().into());
-    // println!("{:#?}", makefile);
+/// runs scuff with the arguments after the program name,
+/// exits with 2 for invalid arguments and 1 for failed commands
+fn exit_code(args: &[String]) -> i32 {
+    let cli = match Cli::parse(args) {
+        Ok(cli) => cli,
+        Err(error) => {
+            eprintln!("error: {}\n\n{}", error, cli::USAGE);
+            return 2;
+        }
+    };
+
+    match run(&cli) {
+        Ok(()) => 0,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("error: {}", error);
+            }
+            1
+        }
+    }
+}
 
-    let mut targets: Vec<(TargetData, Vec<Stmt>)> = vec![];
+fn run(cli: &Cli) -> Result<(), Vec<String>> {
+    let info = |message: String| {
+        if cli.verbosity >= Verbosity::Normal {
+            println!("{}", message);
+        }
+    };
+    let log = |message: String| {
+        if cli.verbosity == Verbosity::Verbose {
+            eprintln!("{}", message);
+        }
+    };
 
-    for target in makefile.targets {
-        let mut lexer = Lexer::new(&target.script);
-        let tokens = lexer.lex();
+    match &cli.command {
+        Command::Help => println!("{}", cli::USAGE),
+        Command::Docs => write_output(cli, scuff::docs())?,
+        Command::New(name) => {
+            scuff::new_project(Path::new(name)).map_err(|error| vec![error.to_string()])?;
+            info(format!("project created in: {}", name));
+        }
+        Command::AddSprite(name) => {
+            scuff::add_sprite(Path::new("."), name).map_err(|error| vec![error.to_string()])?;
+            info(format!("sprite added: {}", name));
+        }
+        Command::EmitTokens => {
+            write_output(cli, scuff::emit_tokens(&cli.project).map_err(messages)?)?
+        }
+        Command::EmitAst => write_output(cli, scuff::emit_ast(&cli.project).map_err(messages)?)?,
+        Command::Build | Command::Check | Command::EmitJson => {
+            log(format!("compiling {}", cli.project.display()));
+            let mut compilation = scuff::compile(&cli.project).map_err(messages)?;
 
-        // println!("------------------------------------------------------------------");
-        // println!("{:#?}", tokens);
-        // println!("------------------------------------------------------------------");
+            if cli.release {
+                compilation.release();
+            }
 
-        let mut parser = Parser::new(tokens);
-        let parsed = parser.parse();
-        println!("{:#?}", parsed);
-        targets.push((target, parsed));
+            if cli.validate {
+                log("validating project.json".to_string());
+                compilation.validate().map_err(messages)?;
+            }
+
+            match cli.command {
+                Command::Build => {
+                    let output_path = cli
+                        .output
+                        .clone()
+                        .unwrap_or_else(|| PathBuf::from("project.sb3"));
+
+                    compilation
+                        .package(&output_path)
+                        .map_err(|error| vec![error.to_string()])?;
+                    info(format!("project written to: {}", output_path.display()));
+                }
+                Command::Check => info(format!("{} has no errors", cli.project.display())),
+                _ => write_output(cli, compilation.project_json())?,
+            }
+        }
     }
 
-    // println!("{:#?}", targets);
+    Ok(())
+}
+
+/// writes to `--output` if given, otherwise to stdout
+fn write_output(cli: &Cli, contents: String) -> Result<(), Vec<String>> {
+    match &cli.output {
+        Some(path) => fs::write(path, contents)
+            .map_err(|error| vec![format!("{}: {}", path.display(), error)]),
+        None => {
+            println!("{}", contents.trim_end());
+            Ok(())
+        }
+    }
+}
 
-    let mut compiler = Compiler::new(targets.clone());
-    (compiler.compile().clone(), targets)
+fn messages(diagnostics: Vec<Diagnostic>) -> Vec<String> {
+    diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect()
 }
@@ -1,10 +1,15 @@
 use serde::{Deserialize, Serialize};
 use std::{
-    fs::{read, read_to_string},
+    collections::HashMap,
+    fmt,
+    ops::{Range, RangeInclusive},
     path::{Path, PathBuf},
 };
+use toml::Spanned;
 
-#[derive(Deserialize, Serialize, Debug)]
+use crate::{files::Files, image};
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub enum Extension {
     Pen,
     Music,
@@ -19,110 +24,698 @@ pub enum Extension {
     Gdxfor,
 }
 
+impl Extension {
+    /// the id scratch uses for the extension in `project.json`,
+    /// this is also the prefix of every opcode it provides
+    pub fn id(&self) -> &'static str {
+        match self {
+            Extension::Pen => "pen",
+            Extension::Music => "music",
+            Extension::VideoSensing => "videoSensing",
+            Extension::Text2Speech => "text2speech",
+            Extension::Translate => "translate",
+            Extension::Makeymakey => "makeymakey",
+            Extension::Microbit => "microbit",
+            Extension::EV3 => "ev3",
+            Extension::Boost => "boost",
+            Extension::Wedo2 => "wedo2",
+            Extension::Gdxfor => "gdxfor",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum RotationStyle {
+    #[default]
+    AllAround,
+    LeftRight,
+    DontRotate,
+}
+
+impl RotationStyle {
+    pub fn id(&self) -> &'static str {
+        match self {
+            RotationStyle::AllAround => "all around",
+            RotationStyle::LeftRight => "left-right",
+            RotationStyle::DontRotate => "don't rotate",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum VideoState {
+    #[default]
+    On,
+    Off,
+    OnFlipped,
+}
+
+impl VideoState {
+    pub fn id(&self) -> &'static str {
+        match self {
+            VideoState::On => "on",
+            VideoState::Off => "off",
+            VideoState::OnFlipped => "on-flipped",
+        }
+    }
+}
+
+/// how files are stored in the sb3, debug builds store them so they're
+/// written faster and `--release` builds deflate them
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Compression {
+    #[default]
+    Stored,
+    Deflate,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 struct Asset {
     pub name: String,
-    pub path: PathBuf,
+    pub path: Spanned<PathBuf>,
+    /// rotation center of a costume, defaults to the middle of the image
+    pub center: Option<[f64; 2]>,
+    /// pixels per stage unit of a bitmap costume
+    pub bitmap_resolution: Option<Spanned<u32>>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 struct Stage {
-    pub name: String,
-    pub script: PathBuf,
-    pub backdrops: Vec<Asset>,
+    pub name: Spanned<String>,
+    pub script: Spanned<PathBuf>,
+    pub backdrops: Spanned<Vec<Asset>>,
+    /// checked to exist, but not packaged into the sb3 yet
     pub sounds: Vec<Asset>,
+    /// index into `backdrops`
+    pub current_costume: Option<Spanned<usize>>,
+    pub tempo: Option<Spanned<f64>>,
+    pub video_state: Option<VideoState>,
+    pub video_transparency: Option<Spanned<f64>>,
+    pub text_to_speech_language: Option<Spanned<String>>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 struct Sprite {
-    pub name: String,
-    pub script: PathBuf,
-    pub costumes: Vec<Asset>,
+    pub name: Spanned<String>,
+    pub script: Spanned<PathBuf>,
+    pub costumes: Spanned<Vec<Asset>>,
+    /// checked to exist, but not packaged into the sb3 yet
     pub sounds: Vec<Asset>,
+    /// index into `costumes`
+    pub current_costume: Option<Spanned<usize>>,
+    pub x: Option<f64>,
+    pub y: Option<f64>,
+    pub size: Option<Spanned<f64>>,
+    pub direction: Option<Spanned<f64>>,
+    pub visible: Option<bool>,
+    pub draggable: Option<bool>,
+    pub rotation_style: Option<RotationStyle>,
+    /// defaults to the order sprites are listed in
+    pub layer_order: Option<Spanned<u32>>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 struct Makefile {
     pub project_name: String,
+    #[serde(default)]
     pub stage: Vec<Stage>,
+    #[serde(default)]
     pub sprite: Vec<Sprite>,
-    pub extensions: Vec<Extension>,
+    /// names of `Extension`s, checked after parsing so errors can point at the entry
+    #[serde(default)]
+    pub extensions: Vec<Spanned<String>>,
+    /// fold constants, strip dead code and inline small functions, see `optimize.rs`,
+    /// without it only functions marked `#[inline]` are inlined
+    #[serde(default)]
+    pub optimize: bool,
+    /// overrides the default of the build profile
+    pub compression: Option<Compression>,
+    /// 0 (fastest) to 9 (smallest), only for deflate
+    pub compression_level: Option<Spanned<i64>>,
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct AssetData {
     pub name: String,
     pub path: PathBuf,
     pub content: Vec<u8>,
+    pub center: Option<(f64, f64)>,
+    pub bitmap_resolution: Option<u32>,
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum TargetProperties {
+    Stage {
+        tempo: f64,
+        video_state: VideoState,
+        video_transparency: f64,
+        text_to_speech_language: Option<String>,
+    },
+    Sprite {
+        x: f64,
+        y: f64,
+        size: f64,
+        direction: f64,
+        visible: bool,
+        draggable: bool,
+        rotation_style: RotationStyle,
+        layer_order: u32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct TargetData {
     pub name: String,
     pub is_stage: bool,
+    /// where `script` was read from, for reporting errors in it
+    pub script_path: PathBuf,
     pub script: String,
     pub costumes: Vec<AssetData>,
     pub sounds: Vec<AssetData>,
+    pub current_costume: usize,
+    pub properties: TargetProperties,
 }
 
 // like Makefile, but contains data from each file listed instead of paths
 #[derive(Debug)]
 pub struct MakefileData {
-    pub project_name: String,
+    /// directory containing project.toml, all paths are relative to it
+    pub project_path: PathBuf,
     pub targets: Vec<TargetData>,
     pub extensions: Vec<Extension>,
+    pub optimize: bool,
+    /// `None` if project.toml doesn't set it, so the build profile can pick
+    pub compression: Option<Compression>,
+    pub compression_level: Option<i64>,
+}
+
+/// a problem with project.toml
+#[derive(Debug, Clone, PartialEq)]
+pub struct MakefileError {
+    pub path: PathBuf,
+    /// path of the key in error, like `sprite[0].costumes[1].path`
+    pub key: String,
+    /// starting from 1
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for MakefileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+
+        if !self.key.is_empty() {
+            write!(f, ": {}", self.key)?;
+        }
+
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for MakefileError {}
+
+/// collects every error in project.toml, so they can all be fixed at once
+struct Validator<'a> {
+    path: &'a Path,
+    files: &'a dyn Files,
+    source: &'a str,
+    errors: Vec<MakefileError>,
+}
+
+/// the locales the text to speech extension can speak
+const TEXT_TO_SPEECH_LANGUAGES: &[&str] = &[
+    "ar", "zh-cn", "da", "nl", "en", "fr", "de", "hi", "is", "it", "ja", "ko", "nb", "pl", "pt-br",
+    "pt", "ro", "ru", "es", "es-419", "sv", "tr", "cy",
+];
+
+impl Validator<'_> {
+    fn error(&mut self, key: &str, span: Option<Range<usize>>, message: String) {
+        self.errors.push(MakefileError {
+            path: self.path.to_path_buf(),
+            key: key.to_string(),
+            line: span.map(|span| self.line(span)),
+            message,
+        });
+    }
+
+    fn line(&self, span: Range<usize>) -> usize {
+        self.source[..span.start.min(self.source.len())]
+            .matches('\n')
+            .count()
+            + 1
+    }
+
+    fn check_range(
+        &mut self,
+        key: &str,
+        value: Option<Spanned<f64>>,
+        range: RangeInclusive<f64>,
+    ) -> Option<f64> {
+        let value = value?;
+
+        if !range.contains(value.get_ref()) {
+            let expected = if range.end().is_infinite() {
+                format!("at least {}", range.start())
+            } else {
+                format!("between {} and {}", range.start(), range.end())
+            };
+
+            self.error(
+                key,
+                Some(value.span()),
+                format!("must be {}, found: {}", expected, value.get_ref()),
+            );
+        }
+
+        Some(value.into_inner())
+    }
+
+    fn check_current_costume(
+        &mut self,
+        key: &str,
+        current_costume: Option<Spanned<usize>>,
+        costumes: usize,
+    ) -> usize {
+        let Some(current_costume) = current_costume else {
+            return 0;
+        };
+
+        if *current_costume.get_ref() >= costumes.max(1) {
+            self.error(
+                key,
+                Some(current_costume.span()),
+                format!(
+                    "is {}, but there are only {} costume(s)",
+                    current_costume.get_ref(),
+                    costumes
+                ),
+            );
+        }
+
+        current_costume.into_inner()
+    }
+
+    fn check_text_to_speech_language(
+        &mut self,
+        key: &str,
+        language: Option<Spanned<String>>,
+    ) -> Option<String> {
+        let language = language?;
+
+        if !TEXT_TO_SPEECH_LANGUAGES.contains(&language.get_ref().as_str()) {
+            self.error(
+                key,
+                Some(language.span()),
+                format!(
+                    "unknown language {:?}, expected one of: {}",
+                    language.get_ref(),
+                    TEXT_TO_SPEECH_LANGUAGES.join(", ")
+                ),
+            );
+        }
+
+        Some(language.into_inner())
+    }
+
+    fn read_script(&mut self, project_path: &Path, key: &str, script: Spanned<PathBuf>) -> String {
+        let path = project_path.join(script.get_ref());
+
+        self.files.read_to_string(&path).unwrap_or_else(|error| {
+            self.error(
+                key,
+                Some(script.span()),
+                format!("could not read {}: {}", path.display(), error),
+            );
+            String::new()
+        })
+    }
+
+    /// `key` is the path of the list in project.toml the assets come from
+    fn read_assets(
+        &mut self,
+        project_path: &Path,
+        assets: Vec<Asset>,
+        key: &str,
+        are_costumes: bool,
+    ) -> Vec<AssetData> {
+        assets
+            .into_iter()
+            .enumerate()
+            .map(|(index, asset)| {
+                let key = format!("{}[{}]", key, index);
+                let path = project_path.join(asset.path.get_ref());
+
+                let data_format = path
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .unwrap_or_default()
+                    .to_lowercase();
+
+                let supported =
+                    !are_costumes || image::COSTUME_FORMATS.contains(&data_format.as_str());
+
+                if !supported {
+                    self.error(
+                        &format!("{}.path", key),
+                        Some(asset.path.span()),
+                        format!(
+                            "unsupported costume format {:?}, expected one of: {}",
+                            data_format,
+                            image::COSTUME_FORMATS.join(", ")
+                        ),
+                    );
+                }
+
+                if let Some(bitmap_resolution) = &asset.bitmap_resolution {
+                    if !image::is_bitmap(&data_format) {
+                        self.error(
+                            &format!("{}.bitmap_resolution", key),
+                            Some(bitmap_resolution.span()),
+                            "only applies to bitmap costumes".to_string(),
+                        );
+                    } else if *bitmap_resolution.get_ref() == 0 {
+                        self.error(
+                            &format!("{}.bitmap_resolution", key),
+                            Some(bitmap_resolution.span()),
+                            "must be at least 1".to_string(),
+                        );
+                    }
+                }
+
+                let content = self.files.read(&path).unwrap_or_else(|error| {
+                    if !supported {
+                        return Vec::new();
+                    }
+
+                    self.error(
+                        &format!("{}.path", key),
+                        Some(asset.path.span()),
+                        format!(
+                            "could not read {} {:?} from {}: {}",
+                            if are_costumes { "costume" } else { "sound" },
+                            asset.name,
+                            path.display(),
+                            error
+                        ),
+                    );
+                    Vec::new()
+                });
+
+                AssetData {
+                    name: asset.name,
+                    content,
+                    path: asset.path.into_inner(),
+                    center: asset.center.map(|[x, y]| (x, y)),
+                    bitmap_resolution: asset.bitmap_resolution.map(Spanned::into_inner),
+                }
+            })
+            .collect()
+    }
 }
 
 impl MakefileData {
-    pub fn parse(makefile_path: PathBuf) -> MakefileData {
-        let makefile: Makefile =
-            toml::from_str(&read_to_string(makefile_path.clone()).unwrap()).unwrap();
+    /// reads project.toml and every file it lists from `files`, reporting all problems found
+    pub fn parse_from(
+        makefile_path: PathBuf,
+        files: &dyn Files,
+    ) -> Result<MakefileData, Vec<MakefileError>> {
+        let source = files.read_to_string(&makefile_path).map_err(|error| {
+            vec![MakefileError {
+                path: makefile_path.clone(),
+                key: String::new(),
+                line: None,
+                message: format!("could not read project file: {}", error),
+            }]
+        })?;
+
+        let mut validator = Validator {
+            path: &makefile_path,
+            files,
+            source: &source,
+            errors: Vec::new(),
+        };
+
+        let makefile: Makefile = match toml::from_str(&source) {
+            Ok(makefile) => makefile,
+            Err(error) => {
+                validator.error("", error.span(), error.message().to_string());
+                return Err(validator.errors);
+            }
+        };
+
+        let project_path = makefile_path.parent().unwrap();
 
-        let project_path = makefile_path.clone();
-        let project_path = project_path.parent().unwrap();
+        // a level only means something for deflate, so setting one picks it
+        let compression = match (makefile.compression, &makefile.compression_level) {
+            (None, Some(_)) => Some(Compression::Deflate),
+            (compression, _) => compression,
+        };
+
+        match (compression, &makefile.compression_level) {
+            (Some(Compression::Stored), Some(level)) => validator.error(
+                "compression_level",
+                Some(level.span()),
+                "can't be set when compression is Stored".to_string(),
+            ),
+            (_, Some(level)) if !(0..=9).contains(level.get_ref()) => validator.error(
+                "compression_level",
+                Some(level.span()),
+                format!("must be between 0 and 9, found: {}", level.get_ref()),
+            ),
+            _ => {}
+        }
+
+        let mut extensions = Vec::new();
+
+        for (index, name) in makefile.extensions.iter().enumerate() {
+            match toml::Value::String(name.get_ref().clone()).try_into::<Extension>() {
+                Ok(extension) => extensions.push(extension),
+                Err(error) => validator.error(
+                    &format!("extensions[{}]", index),
+                    Some(name.span()),
+                    error.message().to_string(),
+                ),
+            }
+        }
+
+        match makefile.stage.len() {
+            0 => validator.error(
+                "stage",
+                None,
+                "a project needs a [[stage]] table".to_string(),
+            ),
+            1 => {}
+            _ => {
+                for (index, stage) in makefile.stage.iter().enumerate().skip(1) {
+                    validator.error(
+                        &format!("stage[{}]", index),
+                        Some(stage.name.span()),
+                        "a project can only have one [[stage]] table".to_string(),
+                    );
+                }
+            }
+        }
 
         let mut targets: Vec<TargetData> = vec![];
 
-        for stage in makefile.stage {
+        for (index, stage) in makefile.stage.into_iter().enumerate() {
+            let key = format!("stage[{}]", index);
+
+            if stage.name.get_ref() != "Stage" {
+                validator.error(
+                    &format!("{}.name", key),
+                    Some(stage.name.span()),
+                    format!(
+                        "the stage must be named \"Stage\", found: {:?}",
+                        stage.name.get_ref()
+                    ),
+                );
+            }
+
+            if stage.backdrops.get_ref().is_empty() {
+                validator.error(
+                    &format!("{}.backdrops", key),
+                    Some(stage.backdrops.span()),
+                    "scratch needs at least one backdrop".to_string(),
+                );
+            }
+
+            let properties = TargetProperties::Stage {
+                tempo: validator
+                    .check_range(&format!("{}.tempo", key), stage.tempo, 20.0..=500.0)
+                    .unwrap_or(60.0),
+                video_state: stage.video_state.unwrap_or_default(),
+                video_transparency: validator
+                    .check_range(
+                        &format!("{}.video_transparency", key),
+                        stage.video_transparency,
+                        0.0..=100.0,
+                    )
+                    .unwrap_or(50.0),
+                text_to_speech_language: validator.check_text_to_speech_language(
+                    &format!("{}.text_to_speech_language", key),
+                    stage.text_to_speech_language,
+                ),
+            };
+
+            let current_costume = validator.check_current_costume(
+                &format!("{}.current_costume", key),
+                stage.current_costume,
+                stage.backdrops.get_ref().len(),
+            );
+
             targets.push(TargetData {
-                name: stage.name,
+                name: stage.name.into_inner(),
                 is_stage: true,
-                script: read_to_string(MakefileData::get_path(project_path, stage.script)).unwrap(),
-                costumes: MakefileData::read_assets(project_path, stage.backdrops),
-                sounds: MakefileData::read_assets(project_path, stage.sounds),
+                script_path: project_path.join(stage.script.get_ref()),
+                script: validator.read_script(
+                    project_path,
+                    &format!("{}.script", key),
+                    stage.script,
+                ),
+                costumes: validator.read_assets(
+                    project_path,
+                    stage.backdrops.into_inner(),
+                    &format!("{}.backdrops", key),
+                    true,
+                ),
+                sounds: validator.read_assets(
+                    project_path,
+                    stage.sounds,
+                    &format!("{}.sounds", key),
+                    false,
+                ),
+                current_costume,
+                properties,
             });
         }
 
-        for sprite in makefile.sprite {
+        // HashMap<SpriteName, Line>
+        let mut sprite_names: HashMap<String, usize> = HashMap::new();
+        // HashMap<LayerOrder, Line>
+        let mut layer_orders: HashMap<u32, usize> = HashMap::new();
+
+        for (index, sprite) in makefile.sprite.into_iter().enumerate() {
+            let key = format!("sprite[{}]", index);
+            let name = sprite.name.get_ref();
+
+            if name == "Stage" || name == "_stage_" {
+                validator.error(
+                    &format!("{}.name", key),
+                    Some(sprite.name.span()),
+                    format!("sprites can't be named {:?}", name),
+                );
+            } else if let Some(line) = sprite_names.get(name) {
+                validator.error(
+                    &format!("{}.name", key),
+                    Some(sprite.name.span()),
+                    format!(
+                        "duplicate sprite name {:?}, first used on line {}",
+                        name, line
+                    ),
+                );
+            } else {
+                sprite_names.insert(name.clone(), validator.line(sprite.name.span()));
+            }
+
+            if sprite.costumes.get_ref().is_empty() {
+                validator.error(
+                    &format!("{}.costumes", key),
+                    Some(sprite.costumes.span()),
+                    "scratch needs at least one costume".to_string(),
+                );
+            }
+
+            if let Some(layer_order) = &sprite.layer_order {
+                if *layer_order.get_ref() == 0 {
+                    validator.error(
+                        &format!("{}.layer_order", key),
+                        Some(layer_order.span()),
+                        "0 is the stage, sprites start at 1".to_string(),
+                    );
+                } else if let Some(line) = layer_orders.get(layer_order.get_ref()) {
+                    validator.error(
+                        &format!("{}.layer_order", key),
+                        Some(layer_order.span()),
+                        format!(
+                            "duplicate layer_order {}, first used on line {}",
+                            layer_order.get_ref(),
+                            line
+                        ),
+                    );
+                } else {
+                    layer_orders.insert(*layer_order.get_ref(), validator.line(layer_order.span()));
+                }
+            }
+
+            let properties = TargetProperties::Sprite {
+                x: sprite.x.unwrap_or(0.0),
+                y: sprite.y.unwrap_or(0.0),
+                size: validator
+                    .check_range(&format!("{}.size", key), sprite.size, 0.0..=f64::INFINITY)
+                    .unwrap_or(100.0),
+                direction: validator
+                    .check_range(
+                        &format!("{}.direction", key),
+                        sprite.direction,
+                        -180.0..=180.0,
+                    )
+                    .unwrap_or(90.0),
+                visible: sprite.visible.unwrap_or(true),
+                draggable: sprite.draggable.unwrap_or(false),
+                rotation_style: sprite.rotation_style.unwrap_or_default(),
+                layer_order: sprite
+                    .layer_order
+                    .map_or(index as u32 + 1, Spanned::into_inner),
+            };
+
+            let current_costume = validator.check_current_costume(
+                &format!("{}.current_costume", key),
+                sprite.current_costume,
+                sprite.costumes.get_ref().len(),
+            );
+
             targets.push(TargetData {
-                name: sprite.name,
+                name: sprite.name.into_inner(),
                 is_stage: false,
-                script: read_to_string(MakefileData::get_path(project_path, sprite.script))
-                    .unwrap(),
-                costumes: MakefileData::read_assets(project_path, sprite.costumes),
-                sounds: MakefileData::read_assets(project_path, sprite.sounds),
+                script_path: project_path.join(sprite.script.get_ref()),
+                script: validator.read_script(
+                    project_path,
+                    &format!("{}.script", key),
+                    sprite.script,
+                ),
+                costumes: validator.read_assets(
+                    project_path,
+                    sprite.costumes.into_inner(),
+                    &format!("{}.costumes", key),
+                    true,
+                ),
+                sounds: validator.read_assets(
+                    project_path,
+                    sprite.sounds,
+                    &format!("{}.sounds", key),
+                    false,
+                ),
+                current_costume,
+                properties,
             });
         }
 
-        MakefileData {
-            project_name: makefile.project_name,
-            targets,
-            extensions: makefile.extensions,
+        if !validator.errors.is_empty() {
+            return Err(validator.errors);
         }
-    }
 
-    fn get_path(project_path: &Path, file_path: PathBuf) -> String {
-        project_path.join(file_path).to_str().unwrap().to_string()
-    }
-
-    fn read_assets(project_path: &Path, assets: Vec<Asset>) -> Vec<AssetData> {
-        assets
-            .iter()
-            .map(|asset| AssetData {
-                name: asset.name.clone(),
-                content: read(MakefileData::get_path(project_path, asset.path.clone())).unwrap(),
-                path: asset.path.clone(),
-            })
-            .collect()
+        Ok(MakefileData {
+            project_path: project_path.to_path_buf(),
+            targets,
+            extensions,
+            optimize: makefile.optimize,
+            compression,
+            compression_level: makefile.compression_level.map(Spanned::into_inner),
+        })
     }
 }
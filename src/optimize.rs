@@ -0,0 +1,530 @@
+use std::{cmp::Ordering, collections::HashSet};
+
+use crate::{
+    makefile::TargetData,
+    parser::{Attribute, Event, Expr, Stmt},
+    token::Operator,
+};
+
+/// functions with at most this many statements are inlined automatically,
+/// which only happens with `optimize = true`
+const INLINE_THRESHOLD: usize = 3;
+
+/// folds constant expressions, removes unreachable code and strips functions and variables
+/// that are never used, constants should already be inlined
+pub fn optimize(targets: Vec<(TargetData, Vec<Stmt>)>) -> Vec<(TargetData, Vec<Stmt>)> {
+    let targets: Vec<(TargetData, Vec<Stmt>)> = targets
+        .into_iter()
+        .map(|(target, ast)| (target, optimize_body(&ast)))
+        .collect();
+
+    // functions called from other targets, e.g. `Stage::reset_board()`
+    let mut remote_calls = HashSet::new();
+    for (_, ast) in &targets {
+        for func_name in called_functions(ast) {
+            if func_name.contains("::") {
+                remote_calls.insert(func_name);
+            }
+        }
+    }
+
+    targets
+        .into_iter()
+        .map(|(target, ast)| {
+            let ast = strip_unused_functions(ast, &target.name, &remote_calls);
+            let ast = strip_unused_variables(ast);
+            let ast = mark_inline_functions(ast);
+            (target, ast)
+        })
+        .collect()
+}
+
+fn optimize_body(body: &[Stmt]) -> Vec<Stmt> {
+    let mut optimized = Vec::new();
+
+    for stmt in body {
+        match stmt {
+            Stmt::If(cond, body_true, body_false) => {
+                let cond = fold_expr(cond);
+                let body_true = optimize_body(body_true);
+                let body_false = body_false
+                    .as_ref()
+                    .map(|body_false| optimize_body(body_false))
+                    .filter(|body_false| !body_false.is_empty());
+
+                match cond {
+                    Expr::Bool(true) => optimized.extend(body_true),
+                    Expr::Bool(false) => optimized.extend(body_false.unwrap_or_default()),
+                    // nothing to run either way
+                    _ if body_true.is_empty() && body_false.is_none() && !has_call(&cond) => {}
+                    _ => optimized.push(Stmt::If(cond, body_true, body_false)),
+                }
+            }
+            Stmt::While(cond, body) => {
+                let cond = fold_expr(cond);
+
+                // an empty loop with a live condition still waits, so only a false one is removed
+                if !matches!(cond, Expr::Bool(false)) {
+                    optimized.push(Stmt::While(cond, optimize_body(body)));
+                }
+            }
+            Stmt::FunctionDeclaration(func_name, args, body, return_type, attributes) => {
+                optimized.push(Stmt::FunctionDeclaration(
+                    func_name.clone(),
+                    args.clone(),
+                    optimize_body(body),
+                    return_type.clone(),
+                    attributes.clone(),
+                ));
+            }
+            Stmt::EventHandler(event, body) => {
+                let event = match event {
+                    Event::Hat(hat_name, args) => {
+                        Event::Hat(hat_name.clone(), args.iter().map(fold_expr).collect())
+                    }
+                    _ => event.clone(),
+                };
+
+                optimized.push(Stmt::EventHandler(event, optimize_body(body)));
+            }
+            Stmt::VariableDeclaration(var_name, var_type, expr) => optimized.push(
+                Stmt::VariableDeclaration(var_name.clone(), var_type.clone(), fold_expr(expr)),
+            ),
+            Stmt::VariableAssignment(var_name, expr) => {
+                optimized.push(Stmt::VariableAssignment(var_name.clone(), fold_expr(expr)))
+            }
+            Stmt::VariableMutation(var_name, op, expr) => optimized.push(Stmt::VariableMutation(
+                var_name.clone(),
+                op.clone(),
+                fold_expr(expr),
+            )),
+            Stmt::FunctionCall(func_name, args) => optimized.push(Stmt::FunctionCall(
+                func_name.clone(),
+                args.iter().map(fold_expr).collect(),
+            )),
+            Stmt::Return(expr) => optimized.push(Stmt::Return(fold_expr(expr))),
+            _ => optimized.push(stmt.clone()),
+        }
+
+        // anything after this is unreachable
+        let ends_script = match optimized.last() {
+            Some(Stmt::Return(_)) => true,
+            Some(Stmt::FunctionCall(func_name, _)) => {
+                func_name == "stop_all" || func_name == "stop_this_script"
+            }
+            _ => false,
+        };
+
+        if ends_script {
+            break;
+        }
+    }
+
+    optimized
+}
+
+fn fold_expr(expr: &Expr) -> Expr {
+    match expr {
+        Expr::Binary(left, op, right) => {
+            let left = fold_expr(left);
+            let right = fold_expr(right);
+
+            fold_binary(&left, op, &right)
+                .unwrap_or_else(|| Expr::Binary(Box::new(left), op.clone(), Box::new(right)))
+        }
+        Expr::FunctionCall(func_name, args) => {
+            Expr::FunctionCall(func_name.clone(), args.iter().map(fold_expr).collect())
+        }
+        Expr::Map(entries) => Expr::Map(
+            entries
+                .iter()
+                .map(|(key, value)| (key.clone(), fold_expr(value)))
+                .collect(),
+        ),
+        _ => expr.clone(),
+    }
+}
+
+/// the value of a binary expression with literal operands, if it can be known at compile time
+fn fold_binary(left: &Expr, op: &Operator, right: &Expr) -> Option<Expr> {
+    if let (Expr::Number(left), Expr::Number(right)) = (left, right) {
+        let value = match op {
+            Operator::Plus => left + right,
+            Operator::Minus => left - right,
+            Operator::Star => left * right,
+            // scratch has its own idea of what dividing by zero gives
+            Operator::Slash if *right != 0.0 => left / right,
+            Operator::Caret => left.powf(*right),
+            _ => f64::NAN,
+        };
+
+        if value.is_finite() {
+            return Some(Expr::Number(value));
+        }
+    }
+
+    if let (Expr::Bool(left), Expr::Bool(right)) = (left, right) {
+        match op {
+            Operator::And => return Some(Expr::Bool(*left && *right)),
+            Operator::Or => return Some(Expr::Bool(*left || *right)),
+            _ => {}
+        }
+    }
+
+    let (left, right) = (literal_string(left)?, literal_string(right)?);
+
+    let value = match op {
+        Operator::Ampersand => return Some(Expr::String(left + &right)),
+        Operator::EqualEqual => compare(&left, &right) == Ordering::Equal,
+        Operator::BangEqual => compare(&left, &right) != Ordering::Equal,
+        Operator::Greater => compare(&left, &right) == Ordering::Greater,
+        Operator::Less => compare(&left, &right) == Ordering::Less,
+        Operator::GreaterEqual => compare(&left, &right) != Ordering::Less,
+        Operator::LessEqual => compare(&left, &right) != Ordering::Greater,
+        _ => return None,
+    };
+
+    Some(Expr::Bool(value))
+}
+
+/// a literal as the string scratch sees
+fn literal_string(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Number(value) => Some(value.to_string()),
+        Expr::String(value) => Some(value.clone()),
+        Expr::Bool(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// compares values like scratch does, as numbers if both are numbers,
+/// otherwise as case insensitive strings
+fn compare(left: &str, right: &str) -> Ordering {
+    let as_number = |value: &str| {
+        if value.trim().is_empty() {
+            return None;
+        }
+
+        value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|value| !value.is_nan())
+    };
+
+    match (as_number(left), as_number(right)) {
+        (Some(left), Some(right)) => left.partial_cmp(&right).unwrap(),
+        _ => left.to_lowercase().cmp(&right.to_lowercase()),
+    }
+}
+
+fn has_call(expr: &Expr) -> bool {
+    match expr {
+        Expr::FunctionCall(_, _) => true,
+        Expr::Binary(left, _, right) => has_call(left) || has_call(right),
+        Expr::Map(entries) => entries.iter().any(|(_, value)| has_call(value)),
+        _ => false,
+    }
+}
+
+/// every expression in a body, including nested bodies
+fn body_exprs(body: &[Stmt]) -> Vec<&Expr> {
+    let mut exprs = Vec::new();
+
+    for stmt in body {
+        match stmt {
+            Stmt::VariableDeclaration(_, _, expr)
+            | Stmt::VariableAssignment(_, expr)
+            | Stmt::VariableMutation(_, _, expr)
+            | Stmt::Return(expr) => exprs.push(expr),
+            Stmt::FunctionCall(_, args) => exprs.extend(args),
+            Stmt::If(cond, body_true, body_false) => {
+                exprs.push(cond);
+                exprs.extend(body_exprs(body_true));
+                if let Some(body_false) = body_false {
+                    exprs.extend(body_exprs(body_false));
+                }
+            }
+            Stmt::While(cond, body) => {
+                exprs.push(cond);
+                exprs.extend(body_exprs(body));
+            }
+            Stmt::EventHandler(event, body) => {
+                if let Event::Hat(_, args) = event {
+                    exprs.extend(args);
+                }
+                exprs.extend(body_exprs(body));
+            }
+            Stmt::FunctionDeclaration(_, _, body, _, _) => exprs.extend(body_exprs(body)),
+            _ => {}
+        }
+    }
+
+    exprs
+}
+
+fn expr_names<'a>(expr: &'a Expr, identifiers: &mut Vec<&'a str>, calls: &mut Vec<&'a str>) {
+    match expr {
+        Expr::Identifier(ident) => identifiers.push(ident),
+        Expr::FunctionCall(func_name, args) => {
+            calls.push(func_name);
+            for arg in args {
+                expr_names(arg, identifiers, calls);
+            }
+        }
+        Expr::Binary(left, _, right) => {
+            expr_names(left, identifiers, calls);
+            expr_names(right, identifiers, calls);
+        }
+        Expr::Map(entries) => {
+            for (_, value) in entries {
+                expr_names(value, identifiers, calls);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// names of every function called in a body, as statements or in expressions
+pub fn called_functions(body: &[Stmt]) -> Vec<String> {
+    let mut calls = Vec::new();
+
+    for expr in body_exprs(body) {
+        expr_names(expr, &mut Vec::new(), &mut calls);
+    }
+
+    let mut calls: Vec<String> = calls.into_iter().map(str::to_string).collect();
+    collect_call_statements(body, &mut calls);
+    calls
+}
+
+fn collect_call_statements(body: &[Stmt], calls: &mut Vec<String>) {
+    for stmt in body {
+        match stmt {
+            Stmt::FunctionCall(func_name, _) => calls.push(func_name.clone()),
+            Stmt::If(_, body_true, body_false) => {
+                collect_call_statements(body_true, calls);
+                if let Some(body_false) = body_false {
+                    collect_call_statements(body_false, calls);
+                }
+            }
+            Stmt::While(_, body)
+            | Stmt::EventHandler(_, body)
+            | Stmt::FunctionDeclaration(_, _, body, _, _) => collect_call_statements(body, calls),
+            _ => {}
+        }
+    }
+}
+
+/// removes functions that can't be reached from an event handler or another target
+fn strip_unused_functions(
+    ast: Vec<Stmt>,
+    target_name: &str,
+    remote_calls: &HashSet<String>,
+) -> Vec<Stmt> {
+    let mut reachable: HashSet<String> = remote_calls
+        .iter()
+        .filter_map(|qualified_name| qualified_name.split_once("::"))
+        .filter(|(name, _)| *name == target_name)
+        .map(|(_, func_name)| func_name.to_string())
+        .collect();
+
+    let handlers: Vec<Stmt> = ast
+        .iter()
+        .filter(|stmt| matches!(stmt, Stmt::EventHandler(_, _)))
+        .cloned()
+        .collect();
+
+    let mut unvisited: Vec<String> = called_functions(&handlers);
+    unvisited.extend(reachable.iter().cloned());
+
+    while let Some(func_name) = unvisited.pop() {
+        reachable.insert(func_name.clone());
+
+        for stmt in &ast {
+            if let Stmt::FunctionDeclaration(name, _, body, _, _) = stmt {
+                if *name == func_name {
+                    unvisited.extend(
+                        called_functions(body)
+                            .into_iter()
+                            .filter(|call| !reachable.contains(call)),
+                    );
+                }
+            }
+        }
+    }
+
+    ast.into_iter()
+        .filter(|stmt| match stmt {
+            Stmt::FunctionDeclaration(func_name, _, _, _, _) => reachable.contains(func_name),
+            _ => true,
+        })
+        .collect()
+}
+
+/// removes variables that are never read, unless setting them calls a function
+fn strip_unused_variables(ast: Vec<Stmt>) -> Vec<Stmt> {
+    let mut ast = ast;
+
+    // removing a variable can leave the variables it read unused
+    loop {
+        let mut read: Vec<&str> = Vec::new();
+        for expr in body_exprs(&ast) {
+            expr_names(expr, &mut read, &mut Vec::new());
+        }
+
+        let read: HashSet<String> = read.into_iter().map(str::to_string).collect();
+        let stripped = strip_writes(&ast, &read);
+
+        if stripped.len() == ast.len() && body_exprs(&stripped).len() == body_exprs(&ast).len() {
+            return stripped;
+        }
+
+        ast = stripped;
+    }
+}
+
+fn strip_writes(body: &[Stmt], read: &HashSet<String>) -> Vec<Stmt> {
+    body.iter()
+        .filter(|stmt| match stmt {
+            Stmt::VariableDeclaration(var_name, _, expr)
+            | Stmt::VariableAssignment(var_name, expr)
+            | Stmt::VariableMutation(var_name, _, expr) => {
+                read.contains(var_name) || has_call(expr)
+            }
+            _ => true,
+        })
+        .map(|stmt| match stmt {
+            Stmt::If(cond, body_true, body_false) => Stmt::If(
+                cond.clone(),
+                strip_writes(body_true, read),
+                body_false
+                    .as_ref()
+                    .map(|body_false| strip_writes(body_false, read)),
+            ),
+            Stmt::While(cond, body) => Stmt::While(cond.clone(), strip_writes(body, read)),
+            Stmt::EventHandler(event, body) => {
+                Stmt::EventHandler(event.clone(), strip_writes(body, read))
+            }
+            Stmt::FunctionDeclaration(func_name, args, body, return_type, attributes) => {
+                Stmt::FunctionDeclaration(
+                    func_name.clone(),
+                    args.clone(),
+                    strip_writes(body, read),
+                    return_type.clone(),
+                    attributes.clone(),
+                )
+            }
+            _ => stmt.clone(),
+        })
+        .collect()
+}
+
+/// adds `#[inline]` to small functions that can be inlined
+fn mark_inline_functions(ast: Vec<Stmt>) -> Vec<Stmt> {
+    ast.iter()
+        .map(|stmt| match stmt {
+            Stmt::FunctionDeclaration(func_name, args, body, return_type, attributes)
+                if statement_count(body) <= INLINE_THRESHOLD
+                    && !attributes.iter().any(|attribute| {
+                        // a label only shows on a custom block, so those are kept
+                        matches!(
+                            attribute,
+                            Attribute::Warp
+                                | Attribute::Recursive
+                                | Attribute::Inline
+                                | Attribute::Label(_)
+                        )
+                    })
+                    && inline_blocker(func_name, body, &ast).is_none() =>
+            {
+                let mut attributes = attributes.clone();
+                attributes.push(Attribute::Inline);
+
+                Stmt::FunctionDeclaration(
+                    func_name.clone(),
+                    args.clone(),
+                    body.clone(),
+                    return_type.clone(),
+                    attributes,
+                )
+            }
+            _ => stmt.clone(),
+        })
+        .collect()
+}
+
+fn statement_count(body: &[Stmt]) -> usize {
+    body.iter()
+        .map(|stmt| match stmt {
+            Stmt::If(_, body_true, body_false) => {
+                1 + statement_count(body_true)
+                    + body_false.as_ref().map_or(0, |body| statement_count(body))
+            }
+            Stmt::While(_, body) => 1 + statement_count(body),
+            _ => 1,
+        })
+        .sum()
+}
+
+/// why a function can't be inlined, if it can't be
+pub fn inline_blocker(func_name: &str, body: &[Stmt], ast: &[Stmt]) -> Option<&'static str> {
+    if !exits_only_at_end(body, true) {
+        return Some("it returns or stops before the end of its body");
+    }
+
+    if contains_loop(body) {
+        return Some("it contains a loop");
+    }
+
+    // follow every call to see if any of them lead back to the function
+    let mut visited: HashSet<String> = HashSet::new();
+    let mut unvisited = called_functions(body);
+
+    while let Some(call) = unvisited.pop() {
+        if call == func_name {
+            return Some("it is recursive");
+        }
+
+        if !visited.insert(call.clone()) {
+            continue;
+        }
+
+        for stmt in ast {
+            if let Stmt::FunctionDeclaration(name, _, body, _, _) = stmt {
+                if *name == call {
+                    unvisited.extend(called_functions(body));
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// `return` may only be the final statement, stopping the script would stop the caller
+fn exits_only_at_end(body: &[Stmt], top_level: bool) -> bool {
+    body.iter().enumerate().all(|(index, stmt)| match stmt {
+        Stmt::Return(_) => top_level && index + 1 == body.len(),
+        Stmt::FunctionCall(func_name, _) => {
+            func_name != "stop_this_script" && func_name != "stop_all"
+        }
+        Stmt::If(_, body_true, body_false) => {
+            exits_only_at_end(body_true, false)
+                && body_false
+                    .as_ref()
+                    .is_none_or(|body| exits_only_at_end(body, false))
+        }
+        _ => true,
+    })
+}
+
+fn contains_loop(body: &[Stmt]) -> bool {
+    body.iter().any(|stmt| match stmt {
+        Stmt::While(_, _) => true,
+        Stmt::If(_, body_true, body_false) => {
+            contains_loop(body_true) || body_false.as_ref().is_some_and(|body| contains_loop(body))
+        }
+        _ => false,
+    })
+}
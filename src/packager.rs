@@ -1,47 +1,141 @@
-use crate::{makefile::TargetData, parser::Stmt, project::Project};
-use md5::{Digest, Md5};
-use std::{fs::File, io::Write, path::PathBuf};
-use zip::{write::SimpleFileOptions, ZipWriter};
+use crate::{assets::AssetStore, image, makefile::Compression, project::Project};
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, Seek, Write},
+    path::{Path, PathBuf},
+};
+use zip::{result::ZipError, write::SimpleFileOptions, CompressionMethod, DateTime, ZipWriter};
 
-// TODO: error handling, please!
+#[derive(Debug)]
+pub enum PackageError {
+    /// (Path, Error)
+    Io(PathBuf, io::Error),
+    Zip(ZipError),
+    Json(serde_json::Error),
+    /// an asset scratch can't load, (ProjectTomlEntry, AssetPath, Extension)
+    UnsupportedAsset(String, PathBuf, String),
+}
+
+impl fmt::Display for PackageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PackageError::Io(path, error) => write!(f, "{}: {}", path.display(), error),
+            PackageError::Zip(error) => write!(f, "could not write sb3: {}", error),
+            PackageError::Json(error) => write!(f, "could not serialise project.json: {}", error),
+            PackageError::UnsupportedAsset(entry, path, extension) => write!(
+                f,
+                "{} ({}): unsupported format {:?}, expected one of: {}",
+                entry,
+                path.display(),
+                extension,
+                image::COSTUME_FORMATS.join(", ")
+            ),
+        }
+    }
+}
 
+impl std::error::Error for PackageError {}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PackageOptions {
+    /// `None` when project.toml doesn't choose one, files are then stored
+    pub compression: Option<Compression>,
+    /// `None` uses the default level of the compression method
+    pub compression_level: Option<i64>,
+}
+
+impl From<ZipError> for PackageError {
+    fn from(error: ZipError) -> Self {
+        PackageError::Zip(error)
+    }
+}
+
+impl From<serde_json::Error> for PackageError {
+    fn from(error: serde_json::Error) -> Self {
+        PackageError::Json(error)
+    }
+}
+
+/// writes the sb3 next to `output_path` first and then renames it into place,
+/// so a failed build never leaves a truncated project behind
 pub fn package_project(
     project: &Project,
-    targets: Vec<(TargetData, Vec<Stmt>)>,
-    output_path: PathBuf,
-) {
-    let file = File::create(output_path).unwrap();
-    let mut zip = ZipWriter::new(file);
+    assets: &AssetStore,
+    options: PackageOptions,
+    output_path: &Path,
+) -> Result<(), PackageError> {
+    let file_name = output_path
+        .file_name()
+        .ok_or_else(|| {
+            PackageError::Io(
+                output_path.to_path_buf(),
+                io::Error::new(io::ErrorKind::InvalidInput, "output path is not a file"),
+            )
+        })?
+        .to_string_lossy();
+    let temp_path = output_path.with_file_name(format!(".{}.tmp", file_name));
 
-    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
-    let json = serde_json::to_string(project).unwrap();
+    let result = File::create(&temp_path)
+        .map_err(|error| PackageError::Io(temp_path.clone(), error))
+        .and_then(|file| write_project(file, project, assets, options))
+        .and_then(|_| {
+            fs::rename(&temp_path, output_path)
+                .map_err(|error| PackageError::Io(output_path.to_path_buf(), error))
+        });
 
-    zip.start_file("project.json", options).unwrap();
-    zip.write_all(&json.into_bytes()).unwrap();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
 
-    let mut written_filenames: Vec<String> = vec![];
+    result
+}
+
+/// the output only depends on the input, timestamps and permissions are fixed
+/// and assets are written in order of their hash
+pub fn write_project<W: Write + Seek>(
+    writer: W,
+    project: &Project,
+    assets: &AssetStore,
+    options: PackageOptions,
+) -> Result<(), PackageError> {
+    let mut zip = ZipWriter::new(writer);
 
-    for target in targets {
-        for costume in target.0.costumes {
-            let extension = costume.path.extension().unwrap().to_str().unwrap();
+    let compression_method = match options.compression.unwrap_or_default() {
+        Compression::Stored => CompressionMethod::Stored,
+        Compression::Deflate => CompressionMethod::Deflated,
+    };
 
-            let mut hasher = Md5::new();
-            hasher.update(&costume.content);
-            let hash = format!("{:x}", hasher.finalize());
+    let options = SimpleFileOptions::default()
+        .compression_method(compression_method)
+        .compression_level(options.compression_level)
+        .last_modified_time(DateTime::default())
+        .unix_permissions(0o644);
+    let json = serde_json::to_string(project)?;
 
-            let filename = format!("{}.{}", hash, extension);
+    zip.start_file("project.json", options)?;
+    zip.write_all(&json.into_bytes())
+        .map_err(|error| PackageError::Io("project.json".into(), error))?;
 
-            if written_filenames.contains(&filename) {
-                continue;
-            }
+    let mut sorted_assets: Vec<_> = assets.iter().collect();
+    sorted_assets.sort_by(|a, b| a.md5ext.cmp(&b.md5ext));
 
-            written_filenames.push(filename.clone());
-            zip.start_file(filename, options).unwrap();
-            zip.write_all(&costume.content).unwrap();
+    for asset in sorted_assets {
+        if !image::COSTUME_FORMATS.contains(&asset.data_format.as_str()) {
+            return Err(PackageError::UnsupportedAsset(
+                asset.entry.clone(),
+                asset.path.clone(),
+                asset.data_format.clone(),
+            ));
         }
 
-        // TODO: sound!!!
+        zip.start_file(asset.md5ext.clone(), options)?;
+        zip.write_all(&asset.content)
+            .map_err(|error| PackageError::Io(asset.md5ext.clone().into(), error))?;
     }
 
-    zip.finish().unwrap();
+    // TODO: sound!!!
+
+    zip.finish()?;
+    Ok(())
 }
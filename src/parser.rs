@@ -1,5 +1,5 @@
 use crate::{
-    error::parse_error,
+    error::{Diagnostic, Result},
     extract,
     token::{Operator, Token, TokenType, Type},
 };
@@ -12,12 +12,14 @@ pub enum Expr {
     FunctionCall(String, Vec<Expr>),
     Bool(bool),
     Binary(Box<Expr>, Operator, Box<Expr>),
+    Map(Vec<(String, Expr)>), // (key, value)
 }
 
 #[derive(Debug, Clone)]
 pub enum Event {
     FlagClicked,
     KeyPressed(Key),
+    Hat(String, Vec<Expr>), // name of a built-in hat block, arguments
 }
 
 #[derive(Debug, Clone)]
@@ -31,26 +33,36 @@ pub enum Key {
     Char(char), // a..=z, 0..=9
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attribute {
+    Warp,          // run without screen refresh
+    Label(String), // label template, e.g. "move %x steps towards %target"
+    Recursive,     // locals live on a stack list
+    Inline,        // substituted at each call site, `optimize = true` adds it to small functions
+}
+
 #[derive(Debug, Clone)]
 pub enum MutationOperator {
-    AddEqual,
-    SubEqual,
-    MultEqual,
-    DivEqual,
+    Add,
+    Sub,
+    Mult,
+    Div,
 }
 
 #[derive(Debug, Clone)]
 pub enum Stmt {
-    Expression(Expr),
     VariableDeclaration(String, Type, Expr), // name, type, value
     VariableAssignment(String, Expr),
     VariableMutation(String, MutationOperator, Expr),
-    FunctionDeclaration(String, Vec<(String, Type)>, Vec<Stmt>, Type), // name, arguments, body, return type
-    EventHandler(Event, Vec<Stmt>),                                    // event, body
-    FunctionCall(String, Vec<Expr>),                                   // name, argumentsparser
+    FunctionDeclaration(String, Vec<(String, Type)>, Vec<Stmt>, Type, Vec<Attribute>), // name, arguments, body, return type, attributes
+    EventHandler(Event, Vec<Stmt>),         // event, body
+    FunctionCall(String, Vec<Expr>),        // name, argumentsparser
     If(Expr, Vec<Stmt>, Option<Vec<Stmt>>), // condition, block if true, block if false
     While(Expr, Vec<Stmt>),                 // condition, block if true
     Return(Expr),
+    Import(String),               // path relative to the project directory
+    Constant(String, Type, Expr), // name, type, value
+    Enum(String, Vec<String>),    // name, variants
 }
 
 pub struct Parser {
@@ -77,33 +89,49 @@ impl Parser {
         self.tokens.get(self.position).unwrap()
     }
 
+    /// an error on the line of the current token
+    fn error(&self, message: String) -> Diagnostic {
+        Diagnostic::at_line(self.current_token_full().line, message)
+    }
+
     fn advance(&mut self) -> TokenType {
         self.position += 1;
         self.current_token()
     }
 
-    fn expect(&mut self, expected: TokenType) -> TokenType {
+    fn expect(&mut self, expected: TokenType) -> Result<TokenType> {
         self.position += 1;
 
         if std::mem::discriminant(&self.current_token()) != std::mem::discriminant(&expected) {
-            parse_error(
-                self.current_token_full().clone(),
-                format!("Expected: {:?}, got: {:?}", expected, self.current_token()),
-            )
+            return Err(self.error(format!(
+                "Expected: {:?}, got: {:?}",
+                expected,
+                self.current_token()
+            )));
         }
 
-        self.current_token()
+        Ok(self.current_token())
     }
 
-    fn expect_type(&mut self) -> Type {
-        extract!(self.expect(TokenType::Type(Type::Void)), TokenType::Type)
+    fn expect_type(&mut self) -> Result<Type> {
+        Ok(extract!(
+            self.expect(TokenType::Type(Type::Void))?,
+            TokenType::Type
+        ))
     }
 
-    fn expect_operator(&mut self) -> Operator {
-        extract!(
-            self.expect(TokenType::Operator(Operator::None)),
+    fn expect_operator(&mut self) -> Result<Operator> {
+        Ok(extract!(
+            self.expect(TokenType::Operator(Operator::None))?,
             TokenType::Operator
-        )
+        ))
+    }
+
+    fn expect_ident(&mut self) -> Result<String> {
+        Ok(extract!(
+            self.expect(TokenType::Ident(String::new()))?,
+            TokenType::Ident
+        ))
     }
 
     fn peek_next(&self) -> TokenType {
@@ -114,39 +142,39 @@ impl Parser {
             .clone()
     }
 
-    pub fn parse(&mut self) -> Vec<Stmt> {
+    pub fn parse(&mut self) -> Result<Vec<Stmt>> {
         let mut statements = Vec::new();
 
-        while self.current_token() != TokenType::EOF {
-            let statement = self.parse_statement();
+        while self.current_token() != TokenType::Eof {
+            let statement = self.parse_statement()?;
             statements.push(statement);
         }
 
-        statements
+        Ok(statements)
     }
 
-    fn parse_block(&mut self) -> Vec<Stmt> {
-        self.expect(TokenType::LeftBrace);
+    fn parse_block(&mut self) -> Result<Vec<Stmt>> {
+        self.expect(TokenType::LeftBrace)?;
         self.advance();
 
         let mut body_statements: Vec<Stmt> = Vec::new();
 
         while self.current_token() != TokenType::RightBrace {
-            body_statements.push(self.parse_statement());
+            body_statements.push(self.parse_statement()?);
         }
 
         self.advance();
 
-        body_statements
+        Ok(body_statements)
     }
 
-    fn parse_function_call(&mut self) -> Vec<Expr> {
-        self.expect(TokenType::LeftParen);
+    fn parse_function_call(&mut self) -> Result<Vec<Expr>> {
+        self.expect(TokenType::LeftParen)?;
 
         let mut args: Vec<Expr> = Vec::new();
 
         while self.peek_next() != TokenType::RightParen {
-            let arg = self.parse_expression();
+            let arg = self.parse_expression()?;
 
             if self.peek_next() == TokenType::Comma {
                 self.advance();
@@ -155,48 +183,47 @@ impl Parser {
             args.push(arg);
         }
 
-        self.expect(TokenType::RightParen);
+        self.expect(TokenType::RightParen)?;
+
+        Ok(args)
+    }
+
+    // Target::function_name
+    fn parse_qualified_name(&mut self, target_name: String) -> Result<String> {
+        self.expect(TokenType::ColonColon)?;
+        let function_name = self.expect_ident()?;
 
-        args
+        Ok(format!("{}::{}", target_name, function_name))
     }
 
-    fn parse_statement(&mut self) -> Stmt {
-        match self.current_token() {
+    fn parse_statement(&mut self) -> Result<Stmt> {
+        let statement = match self.current_token() {
             // let var_name: var_type = expression;
             TokenType::Let => {
-                let ident = extract!(
-                    self.expect(TokenType::Ident(String::new())),
-                    TokenType::Ident
-                );
+                let ident = self.expect_ident()?;
 
-                self.expect(TokenType::Colon);
-                let var_type = self.expect_type();
-                self.expect(TokenType::Equal);
-                let expr = self.parse_expression();
-                self.expect(TokenType::Semicolon);
+                self.expect(TokenType::Colon)?;
+                let var_type = self.expect_type()?;
+                self.expect(TokenType::Equal)?;
+                let expr = self.parse_expression()?;
+                self.expect(TokenType::Semicolon)?;
                 self.advance();
-                return Stmt::VariableDeclaration(ident, var_type, expr);
+                Stmt::VariableDeclaration(ident, var_type, expr)
             }
             // function function_name(arg_name: arg_type) -> return_type { body }
             TokenType::Function => {
-                let function_name = extract!(
-                    self.expect(TokenType::Ident(String::new())),
-                    TokenType::Ident
-                );
+                let function_name = self.expect_ident()?;
 
                 let mut args: Vec<(String, Type)> = Vec::new();
 
-                self.expect(TokenType::LeftParen);
+                self.expect(TokenType::LeftParen)?;
 
                 while self.peek_next() != TokenType::RightParen {
-                    let arg_name = extract!(
-                        self.expect(TokenType::Ident(String::new())),
-                        TokenType::Ident
-                    );
+                    let arg_name = self.expect_ident()?;
 
-                    self.expect(TokenType::Colon);
+                    self.expect(TokenType::Colon)?;
 
-                    let arg_type = self.expect_type();
+                    let arg_type = self.expect_type()?;
 
                     if self.peek_next() == TokenType::Comma {
                         self.advance();
@@ -206,41 +233,100 @@ impl Parser {
                 }
 
                 self.advance();
-                self.expect(TokenType::Arrow);
-                let return_type = self.expect_type();
-                let body_statements = self.parse_block();
+                self.expect(TokenType::Arrow)?;
+                let return_type = self.expect_type()?;
+                let body_statements = self.parse_block()?;
+
+                Stmt::FunctionDeclaration(
+                    function_name,
+                    args,
+                    body_statements,
+                    return_type,
+                    Vec::new(),
+                )
+            }
+            // #[attribute] function ...
+            TokenType::Hash => {
+                let mut attributes = Vec::new();
+
+                while self.current_token() == TokenType::Hash {
+                    self.expect(TokenType::LeftBracket)?;
+
+                    let attribute_name = self.expect_ident()?;
+
+                    let attribute = match attribute_name.as_str() {
+                        "warp" => Attribute::Warp,
+                        "recursive" => Attribute::Recursive,
+                        "inline" => Attribute::Inline,
+                        "label" => {
+                            self.expect(TokenType::LeftParen)?;
+                            let label = extract!(
+                                self.expect(TokenType::String(String::new()))?,
+                                TokenType::String
+                            );
+                            self.expect(TokenType::RightParen)?;
+
+                            Attribute::Label(label)
+                        }
+                        _ => {
+                            return Err(self.error(format!("unknown attribute: {}", attribute_name)))
+                        }
+                    };
 
-                Stmt::FunctionDeclaration(function_name, args, body_statements, return_type)
+                    self.expect(TokenType::RightBracket)?;
+                    self.advance();
+
+                    attributes.push(attribute);
+                }
+
+                let line = self.current_token_full().line;
+
+                match self.parse_statement()? {
+                    Stmt::FunctionDeclaration(function_name, args, body, return_type, _) => {
+                        Stmt::FunctionDeclaration(
+                            function_name,
+                            args,
+                            body,
+                            return_type,
+                            attributes,
+                        )
+                    }
+                    statement => {
+                        return Err(Diagnostic::at_line(
+                            line,
+                            format!(
+                                "attributes can only be applied to functions, found: {:#?}",
+                                statement
+                            ),
+                        ))
+                    }
+                }
             }
             TokenType::Event => {
-                let event_name = extract!(
-                    self.expect(TokenType::Ident(String::new())),
-                    TokenType::Ident
-                );
+                let event_name = self.expect_ident()?;
 
                 let event = match event_name.as_str() {
                     "flag_clicked" => Event::FlagClicked,
                     "key_pressed" => {
-                        self.expect(TokenType::LeftParen);
+                        self.expect(TokenType::LeftParen)?;
 
                         let key = if let TokenType::Number(num) = self.peek_next() {
                             self.advance();
 
                             if num.fract() != 0.0 {
-                                panic!("key press must be whole number");
+                                return Err(
+                                    self.error("key press must be whole number".to_string())
+                                );
                             }
 
                             let num = num as u32;
 
                             match num {
                                 0..=9 => Key::Char(char::from_digit(num, 10).unwrap()),
-                                _ => panic!(),
+                                _ => return Err(self.error(format!("unknown key: {}", num))),
                             }
                         } else {
-                            let key = extract!(
-                                self.expect(TokenType::Ident(String::new())),
-                                TokenType::Ident
-                            );
+                            let key = self.expect_ident()?;
 
                             match key.as_str() {
                                 "any" => Key::Any,
@@ -254,68 +340,93 @@ impl Parser {
 
                                     match char {
                                         'a'..='z' => Key::Char(char),
-                                        _ => panic!(),
+                                        _ => {
+                                            return Err(self.error(format!("unknown key: {}", key)))
+                                        }
                                     }
                                 }
-                                _ => panic!(),
+                                _ => return Err(self.error(format!("unknown key: {}", key))),
                             }
                         };
 
-                        self.expect(TokenType::RightParen);
+                        self.expect(TokenType::RightParen)?;
                         Event::KeyPressed(key)
                     }
-                    _ => panic!(),
+                    _ => {
+                        let args = if self.peek_next() == TokenType::LeftParen {
+                            self.parse_function_call()?
+                        } else {
+                            Vec::new()
+                        };
+
+                        Event::Hat(event_name, args)
+                    }
                 };
 
-                let body = self.parse_block();
+                let body = self.parse_block()?;
 
                 Stmt::EventHandler(event, body)
             }
             TokenType::Ident(ident) => match self.peek_next() {
                 // function call
                 TokenType::LeftParen => {
-                    let args = self.parse_function_call();
-                    self.expect(TokenType::Semicolon);
+                    let args = self.parse_function_call()?;
+                    self.expect(TokenType::Semicolon)?;
                     self.advance();
                     Stmt::FunctionCall(ident, args)
                 }
+                // function call in another target
+                TokenType::ColonColon => {
+                    let func_name = self.parse_qualified_name(ident)?;
+                    let args = self.parse_function_call()?;
+                    self.expect(TokenType::Semicolon)?;
+                    self.advance();
+                    Stmt::FunctionCall(func_name, args)
+                }
                 // variable assignment
                 TokenType::Equal => {
-                    self.expect(TokenType::Equal);
-                    let value = self.parse_expression();
-                    self.expect(TokenType::Semicolon);
+                    self.expect(TokenType::Equal)?;
+                    let value = self.parse_expression()?;
+                    self.expect(TokenType::Semicolon)?;
                     self.advance();
 
                     Stmt::VariableAssignment(ident, value)
                 }
                 TokenType::Operator(op) => {
                     let op = match op {
-                        Operator::PlusEqual => MutationOperator::AddEqual,
-                        Operator::MinusEqual => MutationOperator::SubEqual,
-                        Operator::StarEqual => MutationOperator::MultEqual,
-                        Operator::SlashEqual => MutationOperator::DivEqual,
-                        _ => todo!(),
+                        Operator::PlusEqual => MutationOperator::Add,
+                        Operator::MinusEqual => MutationOperator::Sub,
+                        Operator::StarEqual => MutationOperator::Mult,
+                        Operator::SlashEqual => MutationOperator::Div,
+                        _ => {
+                            return Err(self.error(format!(
+                                "expected +=, -=, *= or /= after {}, found: {:?}",
+                                ident, op
+                            )))
+                        }
                     };
 
                     self.advance();
-                    let value = self.parse_expression();
-                    self.expect(TokenType::Semicolon);
+                    let value = self.parse_expression()?;
+                    self.expect(TokenType::Semicolon)?;
                     self.advance();
 
                     Stmt::VariableMutation(ident, op, value)
                 }
-                _ => panic!(
-                    "unexpected token after parsing ident: {:?}",
-                    self.peek_next()
-                ),
+                _ => {
+                    return Err(self.error(format!(
+                        "unexpected token after parsing ident: {:?}",
+                        self.peek_next()
+                    )))
+                }
             },
             TokenType::If => {
-                let condition = self.parse_expression();
+                let condition = self.parse_expression()?;
 
-                let if_true = self.parse_block();
+                let if_true = self.parse_block()?;
 
                 let if_false = if self.peek_next() == TokenType::LeftBrace {
-                    Some(self.parse_block())
+                    Some(self.parse_block()?)
                 } else {
                     None
                 };
@@ -323,42 +434,132 @@ impl Parser {
                 Stmt::If(condition, if_true, if_false)
             }
             TokenType::While => {
-                let condition = self.parse_expression();
-                let body = self.parse_block();
+                let condition = self.parse_expression()?;
+                let body = self.parse_block()?;
 
                 Stmt::While(condition, body)
             }
+            // const NAME: type = value;
+            TokenType::Const => {
+                let ident = self.expect_ident()?;
+
+                self.expect(TokenType::Colon)?;
+                let const_type = self.expect_type()?;
+                self.expect(TokenType::Equal)?;
+                let value = self.parse_expression()?;
+                self.expect(TokenType::Semicolon)?;
+                self.advance();
+                Stmt::Constant(ident, const_type, value)
+            }
+            // enum Name { Variant, ... }
+            TokenType::Enum => {
+                let enum_name = self.expect_ident()?;
+
+                self.expect(TokenType::LeftBrace)?;
+
+                let mut variants = Vec::new();
+
+                while self.peek_next() != TokenType::RightBrace {
+                    let variant = self.expect_ident()?;
+
+                    if self.peek_next() == TokenType::Comma {
+                        self.advance();
+                    }
+
+                    variants.push(variant);
+                }
+
+                self.expect(TokenType::RightBrace)?;
+                self.advance();
+                Stmt::Enum(enum_name, variants)
+            }
+            // import "path/to/file.scuff";
+            TokenType::Import => {
+                let path = extract!(
+                    self.expect(TokenType::String(String::new()))?,
+                    TokenType::String
+                );
+
+                self.expect(TokenType::Semicolon)?;
+                self.advance();
+                Stmt::Import(path)
+            }
             TokenType::Return => {
-                let return_value = self.parse_expression();
-                println!("{:#?}", return_value);
-                self.expect(TokenType::Semicolon);
+                let return_value = self.parse_expression()?;
+                self.expect(TokenType::Semicolon)?;
                 self.advance();
                 Stmt::Return(return_value)
             }
-            _ => panic!("Unhandled token in statement: {:?}", self.current_token()),
-        }
+            _ => {
+                return Err(self.error(format!(
+                    "Unhandled token in statement: {:?}",
+                    self.current_token()
+                )))
+            }
+        };
+
+        Ok(statement)
     }
 
-    fn parse_expression(&mut self) -> Expr {
-        match self.advance() {
+    fn parse_expression(&mut self) -> Result<Expr> {
+        let expr = match self.advance() {
             TokenType::Number(value) => Expr::Number(value),
             TokenType::String(value) => Expr::String(value),
             TokenType::Ident(value) => match self.peek_next() {
-                TokenType::LeftParen => Expr::FunctionCall(value, self.parse_function_call()),
+                TokenType::LeftParen => Expr::FunctionCall(value, self.parse_function_call()?),
+                TokenType::ColonColon => {
+                    let name = self.parse_qualified_name(value)?;
+
+                    // Target::function() or Enum::Variant
+                    if self.peek_next() == TokenType::LeftParen {
+                        Expr::FunctionCall(name, self.parse_function_call()?)
+                    } else {
+                        Expr::Identifier(name)
+                    }
+                }
                 _ => Expr::Identifier(value),
             },
             TokenType::Bool(value) => Expr::Bool(value),
+            // { KEY: expression, ... }
+            TokenType::LeftBrace => {
+                let mut entries: Vec<(String, Expr)> = Vec::new();
+
+                while self.peek_next() != TokenType::RightBrace {
+                    let key = match self.advance() {
+                        TokenType::Ident(key) | TokenType::String(key) => key,
+                        token => {
+                            return Err(self.error(format!("expected map key, got: {:?}", token)))
+                        }
+                    };
+
+                    self.expect(TokenType::Colon)?;
+                    let value = self.parse_expression()?;
+
+                    if self.peek_next() == TokenType::Comma {
+                        self.advance();
+                    }
+
+                    entries.push((key, value));
+                }
+
+                self.expect(TokenType::RightBrace)?;
+                Expr::Map(entries)
+            }
             TokenType::LeftParen => {
-                let left = self.parse_expression();
-                let op = self.expect_operator();
-                let right = self.parse_expression();
-                self.expect(TokenType::RightParen);
-                return Expr::Binary(Box::new(left), op, Box::new(right));
+                let left = self.parse_expression()?;
+                let op = self.expect_operator()?;
+                let right = self.parse_expression()?;
+                self.expect(TokenType::RightParen)?;
+                Expr::Binary(Box::new(left), op, Box::new(right))
             }
-            _ => panic!(
-                "token type: {:?} not expected in expression",
-                self.current_token()
-            ),
-        }
+            _ => {
+                return Err(self.error(format!(
+                    "token type: {:?} not expected in expression",
+                    self.current_token()
+                )))
+            }
+        };
+
+        Ok(expr)
     }
 }
@@ -1,5 +1,5 @@
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Project {
@@ -20,18 +20,47 @@ impl Project {
     }
 }
 
+impl Default for Project {
+    fn default() -> Project {
+        Project::new()
+    }
+}
+
 type Value = String;
 
+/// maps are written with their keys sorted, so the same source always
+/// produces the same project.json
+fn sorted<S: Serializer, V: Serialize>(
+    map: &HashMap<String, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+fn sorted_option<S: Serializer, V: Serialize>(
+    map: &Option<HashMap<String, V>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    map.as_ref()
+        .map(|map| map.iter().collect::<BTreeMap<_, _>>())
+        .serialize(serializer)
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Target {
     pub is_stage: bool,
     pub name: String,
+    #[serde(serialize_with = "sorted")]
     pub variables: HashMap<String, serde_json::Value>,
+    #[serde(serialize_with = "sorted")]
     pub lists: HashMap<String, (String, Vec<Value>)>,
     // TODO
+    #[serde(serialize_with = "sorted")]
     pub broadcasts: HashMap<String, String>,
+    #[serde(serialize_with = "sorted")]
     pub blocks: HashMap<String, Block>,
+    #[serde(serialize_with = "sorted")]
     pub comments: HashMap<String, Comment>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub current_costume: Option<i32>,
@@ -41,10 +70,34 @@ pub struct Target {
     pub layer_order: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub volume: Option<i32>,
+    // sprite only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direction: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visible: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub draggable: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotation_style: Option<String>,
+    // stage only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tempo: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video_state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video_transparency: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_to_speech_language: Option<String>,
 }
 
-impl Target {
-    pub fn default() -> Target {
+impl Default for Target {
+    fn default() -> Target {
         Target {
             is_stage: true,
             name: "Stage".to_string(),
@@ -58,6 +111,17 @@ impl Target {
             sounds: Vec::new(),
             layer_order: None,
             volume: None,
+            x: None,
+            y: None,
+            size: None,
+            direction: None,
+            visible: None,
+            draggable: None,
+            rotation_style: None,
+            tempo: None,
+            video_state: None,
+            video_transparency: None,
+            text_to_speech_language: None,
         }
     }
 }
@@ -82,7 +146,10 @@ pub struct Block {
     ///
     /// If there is an obscured shadow, the third element is its ID or
     /// an array representing it.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "sorted_option"
+    )]
     pub inputs: Option<HashMap<String, serde_json::Value>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub fields: Option<serde_json::Value>,
@@ -155,18 +222,21 @@ pub struct Costume {
     pub asset_id: String,
     pub md5ext: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub rotation_center_x: Option<i32>,
+    pub bitmap_resolution: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotation_center_x: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub rotation_center_y: Option<i32>,
+    pub rotation_center_y: Option<f64>,
 }
 
-impl Costume {
-    pub fn default() -> Costume {
+impl Default for Costume {
+    fn default() -> Costume {
         Costume {
             name: "backdrop1".to_string(),
             data_format: "svg".to_string(),
             asset_id: "cd21514d0531fdffb22204e0ec5ed84a".to_string(),
             md5ext: "cd21514d0531fdffb22204e0ec5ed84a.svg".to_string(),
+            bitmap_resolution: None,
             rotation_center_x: None,
             rotation_center_y: None,
         }
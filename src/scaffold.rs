@@ -0,0 +1,124 @@
+use std::{
+    fs::{self, read_to_string, OpenOptions},
+    io::{self, Write},
+    path::Path,
+};
+
+/// the empty backdrop scratch gives every new project, its hash is `Costume::default`'s asset id
+pub const DEFAULT_BACKDROP_SVG: &str = "<svg version=\"1.1\" width=\"2\" height=\"2\" viewBox=\"-1 -1 2 2\" xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\">\n  <!-- Exported by Scratch - http://scratch.mit.edu/ -->\n</svg>";
+
+/// a circle, so new sprites can be seen on the stage
+pub const DEFAULT_COSTUME_SVG: &str = r##"<svg version="1.1" width="48" height="48" viewBox="0 0 48 48" xmlns="http://www.w3.org/2000/svg">
+  <circle cx="24" cy="24" r="22" fill="#ffab19" stroke="#cf8b17" stroke-width="4"/>
+</svg>
+"##;
+
+const HELLO_WORLD: &str = "event flag_clicked {\n\tsay(\"Hello, world!\");\n}\n";
+
+/// creates a project directory with a stage, one sprite and a hello world script,
+/// the project is named after the directory
+pub fn new_project(path: &Path) -> io::Result<()> {
+    if path.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", path.display()),
+        ));
+    }
+
+    let project_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "expected a project name"))?;
+
+    fs::create_dir_all(path)?;
+
+    let makefile = format!(
+        "project_name = {}\nextensions = []\n\n\
+        [[stage]]\n\
+        name = \"Stage\"\n\
+        script = \"stage.scuff\"\n\
+        backdrops = [{{ name = \"backdrop1\", path = \"backdrop1.svg\" }}]\n\
+        sounds = []\n",
+        toml_string(&project_name)
+    );
+
+    fs::write(path.join("project.toml"), makefile)?;
+    fs::write(path.join("stage.scuff"), "")?;
+    fs::write(path.join("backdrop1.svg"), DEFAULT_BACKDROP_SVG)?;
+
+    add_sprite(path, "sprite1")?;
+    fs::write(path.join("sprite1.scuff"), HELLO_WORLD)
+}
+
+/// appends a `[[sprite]]` table to project.toml along with an empty script and a costume
+pub fn add_sprite(project_path: &Path, name: &str) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+
+    if name.is_empty() || name == "Stage" || name == "_stage_" {
+        return Err(invalid(format!(
+            "{:?} can't be used as a sprite name",
+            name
+        )));
+    }
+
+    // the name is also used for the sprite's files
+    if !name
+        .chars()
+        .all(|char| char.is_alphanumeric() || char == '_' || char == '-')
+    {
+        return Err(invalid(format!(
+            "sprite name {:?} can only contain letters, numbers, '_' and '-'",
+            name
+        )));
+    }
+
+    let makefile_path = project_path.join("project.toml");
+    let makefile: toml::Table = read_to_string(&makefile_path)?
+        .parse()
+        .map_err(|error| invalid(format!("{}: {}", makefile_path.display(), error)))?;
+
+    let sprite_exists = makefile
+        .get("sprite")
+        .and_then(|sprites| sprites.as_array())
+        .is_some_and(|sprites| {
+            sprites
+                .iter()
+                .any(|sprite| sprite.get("name").and_then(|name| name.as_str()) == Some(name))
+        });
+
+    if sprite_exists {
+        return Err(invalid(format!("sprite {:?} already exists", name)));
+    }
+
+    let script = format!("{}.scuff", name);
+    let costume = format!("{}_costume1.svg", name);
+
+    for file in [&script, &costume] {
+        if project_path.join(file).exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", project_path.join(file).display()),
+            ));
+        }
+    }
+
+    fs::write(project_path.join(&script), "")?;
+    fs::write(project_path.join(&costume), DEFAULT_COSTUME_SVG)?;
+
+    let mut file = OpenOptions::new().append(true).open(&makefile_path)?;
+    write!(
+        file,
+        "\n[[sprite]]\n\
+        name = {}\n\
+        script = {}\n\
+        costumes = [{{ name = \"costume1\", path = {} }}]\n\
+        sounds = []\n",
+        toml_string(name),
+        toml_string(&script),
+        toml_string(&costume)
+    )
+}
+
+fn toml_string(string: &str) -> String {
+    toml::Value::String(string.to_string()).to_string()
+}
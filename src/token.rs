@@ -53,6 +53,8 @@ pub enum TokenType {
     Dot,
     Semicolon,
     Colon,
+    ColonColon,
+    Hash,
     Equal,
     Arrow,
 
@@ -70,14 +72,17 @@ pub enum TokenType {
     Continue,
     Return,
     Let,
+    Const,
+    Enum,
 
     While,
     For,
 
     Function,
     Event,
+    Import,
 
-    EOF,
+    Eof,
 }
 
 #[derive(Debug, Clone)]
@@ -90,10 +95,6 @@ impl Token {
     pub fn new(token_type: TokenType, line: usize) -> Token {
         Token { token_type, line }
     }
-
-    pub fn to_string(&self) -> String {
-        format!("{:?}", self.token_type)
-    }
 }
 
 // hacky macro for extracting the value of an enum of known type
@@ -3,7 +3,7 @@ use jsonschema::{Draft, JSONSchema};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
-pub fn validate_project(project: &Project) {
+pub fn validate_project(project: &Project) -> Result<(), Vec<String>> {
     let schema_json = json!({
       "$id":"https://scratch.mit.edu/sb3_schema.json",
       "$schema":"http://json-schema.org/schema#",
@@ -689,13 +689,14 @@ pub fn validate_project(project: &Project) {
         serde_json::from_str(&project_json).expect("Invalid JSON blob");
     let result = compiled_schema.validate(&project_json_parsed);
 
-    match result {
-        Ok(()) => println!("JSON is valid."),
-        Err(errors) => {
-            println!("JSON is invalid. Errors:");
-            for error in errors {
-                println!("{}", error);
-            }
-        }
-    }
+    result.map_err(|errors| {
+        errors
+            .map(|error| {
+                format!(
+                    "project.json is invalid at {}: {}",
+                    error.instance_path, error
+                )
+            })
+            .collect()
+    })
 }